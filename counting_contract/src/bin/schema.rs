@@ -1,10 +1,11 @@
 use cosmwasm_schema::write_api;
-use counting_contract::msg::{ExecMsg, InstantiateMsg, QueryMsg};
- 
+use counting_contract::msg::{ExecMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecMsg,
         query: QueryMsg,
+        migrate: MigrateMsg,
     }
-}
\ No newline at end of file
+}
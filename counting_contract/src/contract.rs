@@ -1,226 +1,3571 @@
-use cosmwasm_std::{Coin, DepsMut, Response, StdResult, MessageInfo};
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order, Reply,
+    Response, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw_storage_plus::Bound;
 
-use crate::state::{COUNTER, MINIMAL_DONATION, OWNER};
+use crate::error::ContractError;
+use crate::msg::{
+    BadgeThresholds, CounterChangeCause, CountingPolicy, InstantiateMsg, LimitMode, Unbonding,
+    VestingSchedule,
+};
+use crate::state::{
+    legacy::{self, ChangeSet, MigrationCursor, Stored},
+    CounterChange, CounterState, FailedPayout, Payout, ACCEPTED_CW20, BADGE_THRESHOLDS, COUNTER,
+    COUNTER_HISTORY, COUNTER_LIMIT, COUNTING_POLICY, ESCROW, FAILED_PAYOUTS, HELD_CW20,
+    LAST_COUNTER_CHANGE_ID, MATCHING_POOLS, MINIMAL_DONATIONS, OWNER, PENDING_WITHDRAWALS,
+    REFUND_MODE, RESERVES, SENT_PAYOUTS, STAKING, STRICT_MODE, UNBONDING, VESTING, WITHDRAWN,
+};
 
 // there's no creator added to the instantiation message
 // we are relying on who sends the instantiation message
 pub fn instantiate(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    counter: u64,
-    minimal_donation: Coin
-) -> StdResult<Response> {
-    COUNTER.save(deps.storage, &counter)?;
-    // initializing a minimal donation
-    MINIMAL_DONATION.save(deps.storage, &minimal_donation)?;
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    save_counter(
+        deps.storage,
+        &env,
+        msg.counter,
+        CounterChangeCause::Instantiate,
+    )?;
+    // initializing minimal donations for all accepted denoms
+    for minimal_donation in &msg.minimal_donations {
+        save_minimal_donation(deps.storage, minimal_donation)?;
+    }
+    for token in &msg.accepted_cw20s {
+        let address = deps.api.addr_validate(&token.address)?;
+        save_accepted_cw20(deps.storage, &address, token.amount)?;
+    }
+    validate_counting_policy(&msg.counting_policy)?;
+    COUNTING_POLICY.save(deps.storage, &msg.counting_policy)?;
+    REFUND_MODE.save(deps.storage, &msg.refund_mode)?;
+    STRICT_MODE.save(deps.storage, &msg.strict)?;
+    validate_badge_thresholds(&msg.badge_thresholds)?;
+    BADGE_THRESHOLDS.save(deps.storage, &msg.badge_thresholds)?;
+    if let Some(vesting) = &msg.vesting {
+        if let VestingSchedule::Linear { start, end } = vesting {
+            if end <= start {
+                return Err(ContractError::InvalidVesting {});
+            }
+        }
+        VESTING.save(deps.storage, vesting)?;
+    }
     // initializing the owner on instantiation
     OWNER.save(deps.storage, &info.sender)?;
 
-    Ok(Response::new())
-}
+    Ok(Response::new())
+}
+
+// every reply is an outcome of a payout, the reply id is the payout id
+// a failed payout is recorded to be retried, instead of failing the whole withdrawal
+pub fn reply(deps: DepsMut, reply: Reply) -> Result<Response, ContractError> {
+    let payout_id = reply.id;
+    let payout = SENT_PAYOUTS
+        .may_load(deps.storage, payout_id)?
+        .ok_or(ContractError::PayoutNotFound { payout_id })?;
+    SENT_PAYOUTS.remove(deps.storage, payout_id);
+
+    let resp = Response::new()
+        .add_attribute("action", "payout")
+        .add_attribute("payout_id", payout_id.to_string());
+
+    match reply.result.into_result() {
+        Ok(_) => Ok(resp.add_attribute("outcome", "sent")),
+        Err(error) => {
+            let resp = resp
+                .add_attribute("outcome", "failed")
+                .add_attribute("error", &error);
+            FAILED_PAYOUTS.save(deps.storage, payout_id, &FailedPayout { payout, error })?;
+
+            Ok(resp)
+        }
+    }
+}
+
+// every change of the default counter is snapshotted at the block height and logged with its cause
+fn save_counter(
+    storage: &mut dyn Storage,
+    env: &Env,
+    value: Uint128,
+    cause: CounterChangeCause,
+) -> StdResult<()> {
+    COUNTER.save(storage, &value, env.block.height)?;
+
+    let change_id = LAST_COUNTER_CHANGE_ID
+        .may_load(storage)?
+        .unwrap_or_default()
+        + 1;
+    let change = CounterChange {
+        value,
+        height: env.block.height,
+        time: env.block.time,
+        cause,
+    };
+    COUNTER_HISTORY.save(storage, change_id, &change)?;
+    LAST_COUNTER_CHANGE_ID.save(storage, &change_id)
+}
+
+// a zero minimal donation would count any message, even the ones without funds
+fn save_minimal_donation(
+    storage: &mut dyn Storage,
+    minimal_donation: &Coin,
+) -> Result<(), ContractError> {
+    if minimal_donation.amount.is_zero() {
+        return Err(ContractError::InvalidMinimalDonation {
+            denom: minimal_donation.denom.clone(),
+        });
+    }
+
+    MINIMAL_DONATIONS.save(storage, &minimal_donation.denom, &minimal_donation.amount)?;
+    Ok(())
+}
+
+fn save_accepted_cw20(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    minimal_donation: Uint128,
+) -> Result<(), ContractError> {
+    if minimal_donation.is_zero() {
+        return Err(ContractError::InvalidMinimalDonation {
+            denom: address.to_string(),
+        });
+    }
+
+    ACCEPTED_CW20.save(storage, address, &minimal_donation)?;
+    Ok(())
+}
+
+// tiers are searched from the highest one, so they have to be sorted
+fn validate_counting_policy(policy: &CountingPolicy) -> Result<(), ContractError> {
+    if let CountingPolicy::Tiered { tiers } = policy {
+        let mut previous = Uint128::zero();
+        for tier in tiers {
+            if tier.threshold <= previous {
+                return Err(ContractError::InvalidTiers {});
+            }
+            previous = tier.threshold;
+        }
+    }
+
+    Ok(())
+}
+
+// a zero threshold would award the tier on the first donation
+// and silver at or above gold would skip the silver tier
+fn validate_badge_thresholds(thresholds: &BadgeThresholds) -> Result<(), ContractError> {
+    if thresholds.silver.is_zero() || thresholds.silver >= thresholds.gold {
+        return Err(ContractError::InvalidBadgeThresholds {});
+    }
+
+    Ok(())
+}
+
+// calculates how much the counter is incremented by the qualifying coins
+// every coin comes together with the minimal donation of its denom
+// all the additions are checked, so an overflow fails with an error instead of wrapping around
+fn counter_increment(
+    policy: &CountingPolicy,
+    qualifying: &[(&Coin, Uint128)],
+) -> Result<Uint128, ContractError> {
+    let units = |coin: &Coin, minimal: Uint128| -> StdResult<Uint128> {
+        Ok(coin.amount.checked_div(minimal)?)
+    };
+
+    let increment = match policy {
+        CountingPolicy::OncePerMessage => Uint128::from(u8::from(!qualifying.is_empty())),
+        CountingPolicy::OncePerCoin => Uint128::from(qualifying.len() as u128),
+        CountingPolicy::Proportional => {
+            let mut increment = Uint128::zero();
+            for (coin, minimal) in qualifying {
+                increment = increment.checked_add(units(coin, *minimal)?)?;
+            }
+            increment
+        }
+        CountingPolicy::Tiered { tiers } => {
+            let mut increment = Uint128::zero();
+            for (coin, minimal) in qualifying {
+                let units = units(coin, *minimal)?;
+                let tier_increment = tiers
+                    .iter()
+                    .rev()
+                    .find(|tier| units >= tier.threshold)
+                    .map_or(0, |tier| tier.increment);
+                increment = increment.checked_add(Uint128::from(tier_increment))?;
+            }
+            increment
+        }
+    };
+
+    Ok(increment)
+}
+
+// keeps the counter value within the configured maximum
+// saturating stops at the maximum, rejecting fails the whole message
+fn limit_counter(storage: &dyn Storage, value: Uint128) -> Result<Uint128, ContractError> {
+    match COUNTER_LIMIT.may_load(storage)? {
+        Some(limit) if value > limit.max => match limit.on_reached {
+            LimitMode::Saturate => Ok(limit.max),
+            LimitMode::Reject => Err(ContractError::CounterLimitReached { max: limit.max }),
+        },
+        _ => Ok(value),
+    }
+}
+
+// counters were stored as u64 before they were widened to Uint128
+// every value still in the old format is rewritten in place under the same key
+// the values which are already widened are left alone, so running the migration again changes nothing
+// the single minimal donation of the first version becomes the first accepted denom
+// the maps are walked at most `limit` rows per call, the cursor keeps the place for the next call
+// -> the migration is repeated with the same code until it reports it is finished
+pub fn migrate(deps: DepsMut, limit: Option<u32>) -> Result<Response, ContractError> {
+    const DEFAULT_LIMIT: u32 = 100;
+
+    let mut migrated = 0;
+
+    if let Some(minimal) = legacy::MINIMAL_DONATION.may_load(deps.storage)? {
+        // the zero minimum used to count every donation, the smallest amount does the same now
+        let amount = minimal.amount.max(Uint128::one());
+        MINIMAL_DONATIONS.save(deps.storage, &minimal.denom, &amount)?;
+        legacy::MINIMAL_DONATION.remove(deps.storage);
+        migrated += 1;
+    }
+
+    if let Some(Stored::Old(value)) = legacy::COUNTER.may_load(deps.storage)? {
+        legacy::COUNTER.save(deps.storage, &Stored::Wide(Uint128::from(value)))?;
+        migrated += 1;
+    }
+
+    // the named counters go first, they can't be used at all before they are migrated
+    // one row more than the budget is read, to know if the map is done without another call
+    let mut budget = limit.unwrap_or(DEFAULT_LIMIT) as usize;
+    let mut cursor = legacy::MIGRATION_CURSOR
+        .may_load(deps.storage)?
+        .unwrap_or(MigrationCursor::Counters { start_after: None });
+
+    let finished = loop {
+        if budget == 0 {
+            legacy::MIGRATION_CURSOR.save(deps.storage, &cursor)?;
+            break false;
+        }
+
+        cursor = match cursor {
+            MigrationCursor::Counters { start_after } => {
+                let mut counters = legacy::COUNTERS
+                    .range(
+                        deps.storage,
+                        start_after.as_deref().map(Bound::exclusive),
+                        None,
+                        Order::Ascending,
+                    )
+                    .take(budget + 1)
+                    .collect::<StdResult<Vec<_>>>()?;
+                let exhausted = counters.len() <= budget;
+                counters.truncate(budget);
+                budget -= counters.len();
+                let last = counters.last().map(|(name, _)| name.clone());
+
+                for (name, counter) in counters {
+                    if let Stored::Old(counter) = counter {
+                        let counter = CounterState {
+                            value: Uint128::from(counter.value),
+                            owner: counter.owner,
+                            minimal_donations: counter.minimal_donations,
+                            counting_policy: counter.counting_policy,
+                        };
+                        legacy::COUNTERS.save(deps.storage, &name, &Stored::Wide(counter))?;
+                        migrated += 1;
+                    }
+                }
+
+                if exhausted {
+                    MigrationCursor::History { start_after: None }
+                } else {
+                    MigrationCursor::Counters { start_after: last }
+                }
+            }
+            MigrationCursor::History { start_after } => {
+                let mut history = legacy::COUNTER_HISTORY
+                    .range(
+                        deps.storage,
+                        start_after.map(Bound::exclusive),
+                        None,
+                        Order::Ascending,
+                    )
+                    .take(budget + 1)
+                    .collect::<StdResult<Vec<_>>>()?;
+                let exhausted = history.len() <= budget;
+                history.truncate(budget);
+                budget -= history.len();
+                let last = history.last().map(|(change_id, _)| *change_id);
+
+                for (change_id, change) in history {
+                    if let Stored::Old(change) = change {
+                        let change = CounterChange {
+                            value: Uint128::from(change.value),
+                            height: change.height,
+                            time: change.time,
+                            cause: change.cause,
+                        };
+                        legacy::COUNTER_HISTORY.save(
+                            deps.storage,
+                            change_id,
+                            &Stored::Wide(change),
+                        )?;
+                        migrated += 1;
+                    }
+                }
+
+                if exhausted {
+                    MigrationCursor::Changelog { start_after: None }
+                } else {
+                    MigrationCursor::History { start_after: last }
+                }
+            }
+            MigrationCursor::Changelog { start_after } => {
+                let mut changelog = legacy::COUNTER_CHANGELOG
+                    .range(
+                        deps.storage,
+                        start_after.map(Bound::exclusive),
+                        None,
+                        Order::Ascending,
+                    )
+                    .take(budget + 1)
+                    .collect::<StdResult<Vec<_>>>()?;
+                let exhausted = changelog.len() <= budget;
+                changelog.truncate(budget);
+                budget -= changelog.len();
+                let last = changelog.last().map(|(height, _)| *height);
+
+                for (height, change) in changelog {
+                    // the change without an old value looks the same in both formats, so it is kept as it is
+                    if let Stored::Old(ChangeSet { old: Some(old) }) = change {
+                        let change = ChangeSet {
+                            old: Some(Uint128::from(old)),
+                        };
+                        legacy::COUNTER_CHANGELOG.save(
+                            deps.storage,
+                            height,
+                            &Stored::Wide(change),
+                        )?;
+                        migrated += 1;
+                    }
+                }
+
+                if exhausted {
+                    // the next migration starts over, finding nothing left in the old format
+                    legacy::MIGRATION_CURSOR.remove(deps.storage);
+                    break true;
+                }
+                MigrationCursor::Changelog { start_after: last }
+            }
+        };
+    };
+
+    let resp = Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("migrated", migrated.to_string())
+        .add_attribute("finished", finished.to_string());
+
+    Ok(resp)
+}
+
+// the part of a qualifying coin which was taken into account by the counting policy
+// anything above it is an excess which can be refunded
+fn counted_amount(policy: &CountingPolicy, coin: &Coin, minimal: Uint128) -> StdResult<Uint128> {
+    let units = coin.amount.checked_div(minimal)?;
+
+    let counted_units = match policy {
+        CountingPolicy::OncePerMessage | CountingPolicy::OncePerCoin => Uint128::one(),
+        CountingPolicy::Proportional => units,
+        // coins below the first tier met the minimal donation anyway
+        CountingPolicy::Tiered { tiers } => tiers
+            .iter()
+            .rev()
+            .find(|tier| units >= tier.threshold)
+            .map_or(Uint128::one(), |tier| tier.threshold),
+    };
+
+    Ok(minimal.checked_mul(counted_units)?)
+}
+
+// formats coins for the response attributes, like "10atom,5osmo"
+fn coins_to_string(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(Coin::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// adds the coin to the list, merging it with the coin of the same denom if there is one
+fn add_coin(coins: &mut Vec<Coin>, coin: &Coin) {
+    match coins.iter_mut().find(|c| c.denom == coin.denom) {
+        Some(existing) => existing.amount += coin.amount,
+        None => coins.push(coin.clone()),
+    }
+}
+
+// subtracts the coin from the list, dropping the denom once nothing is left of it
+fn sub_coin(coins: &mut Vec<Coin>, coin: &Coin) {
+    if let Some(existing) = coins.iter_mut().find(|c| c.denom == coin.denom) {
+        existing.amount = existing.amount.saturating_sub(coin.amount);
+    }
+    coins.retain(|c| !c.amount.is_zero());
+}
+
+// the part of the contract balance which is not held for anything else
+// funds escrowed for campaigns belong to their donors until the campaign is claimed,
+// what is left of matching pools belongs to their sponsors,
+// queued withdrawals are held until they are executed or cancelled,
+// and failed payouts until they are retried
+fn withdrawable_balance(deps: Deps, env: &Env) -> StdResult<Vec<Coin>> {
+    let mut balance = deps.querier.query_all_balances(&env.contract.address)?;
+    for coin in ESCROW.may_load(deps.storage)?.unwrap_or_default() {
+        sub_coin(&mut balance, &coin);
+    }
+    for pool in MATCHING_POOLS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, pool) = pool?;
+        sub_coin(&mut balance, &pool.remaining);
+    }
+    for withdrawal in PENDING_WITHDRAWALS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, withdrawal) = withdrawal?;
+        for coin in &withdrawal.funds {
+            sub_coin(&mut balance, coin);
+        }
+    }
+    for failed in FAILED_PAYOUTS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, failed) = failed?;
+        for coin in &failed.payout.funds {
+            sub_coin(&mut balance, coin);
+        }
+    }
+
+    Ok(balance)
+}
+
+// the part of the balance of the denom released by the vesting schedule and not withdrawn yet,
+// without touching the reserve of the denom
+// the schedule applies to everything the contract had to withdraw, including what was withdrawn
+// staked funds are not in the balance, but they still count into what the contract received
+fn available_amount(deps: Deps, env: &Env, denom: &str, balance: Uint128) -> StdResult<Uint128> {
+    let withdrawn = WITHDRAWN.may_load(deps.storage, denom)?.unwrap_or_default();
+    let staked = staked_balance(deps, env)?
+        .into_iter()
+        .find(|coin| coin.denom == denom)
+        .map_or(Uint128::zero(), |coin| coin.amount);
+    let total = balance + withdrawn + staked;
+    let vested = match VESTING.may_load(deps.storage)? {
+        Some(schedule) => schedule.vested(total, env.block.time),
+        None => total,
+    };
+    let reserve = RESERVES.may_load(deps.storage, denom)?.unwrap_or_default();
+
+    Ok(vested
+        .saturating_sub(withdrawn)
+        .min(balance.saturating_sub(reserve)))
+}
+
+// the withdrawable balance limited to what was already vested and is above the reserve
+// this is what the owner can actually pull, or the payees get distributed
+fn available_balance(deps: Deps, env: &Env) -> StdResult<Vec<Coin>> {
+    let mut balance = withdrawable_balance(deps, env)?;
+    for coin in &mut balance {
+        coin.amount = available_amount(deps, env, &coin.denom, coin.amount)?;
+    }
+    balance.retain(|coin| !coin.amount.is_zero());
+
+    Ok(balance)
+}
+
+fn available_cw20_balance(deps: Deps, env: &Env) -> StdResult<Vec<Cw20Coin>> {
+    let mut balance = cw20_balances(deps, env)?;
+    for token in &mut balance {
+        token.amount = available_amount(deps, env, &token.address, token.amount)?;
+    }
+    balance.retain(|token| !token.amount.is_zero());
+
+    Ok(balance)
+}
+
+// unbondings which are not completed yet, the completed ones are back in the balance
+fn pending_unbonding(storage: &dyn Storage, env: &Env) -> StdResult<Vec<Unbonding>> {
+    let mut unbonding = UNBONDING.may_load(storage)?.unwrap_or_default();
+    unbonding.retain(|unbonding| unbonding.completion > env.block.time);
+
+    Ok(unbonding)
+}
+
+// funds delegated to the validator or still unbonding from it
+fn staked_balance(deps: Deps, env: &Env) -> StdResult<Vec<Coin>> {
+    let mut staked = vec![];
+    if let Some(staking) = STAKING.may_load(deps.storage)? {
+        let delegation = deps
+            .querier
+            .query_delegation(&env.contract.address, &staking.validator)?;
+        if let Some(delegation) = delegation {
+            add_coin(&mut staked, &delegation.amount);
+        }
+    }
+    for unbonding in pending_unbonding(deps.storage, env)? {
+        add_coin(&mut staked, &unbonding.amount);
+    }
+
+    Ok(staked)
+}
+
+// everything sent out of the vested funds has to be recorded, so it's not released again
+fn record_withdrawn(
+    storage: &mut dyn Storage,
+    coins: &[Coin],
+    tokens: &[Cw20Coin],
+) -> StdResult<()> {
+    let withdrawn = coins
+        .iter()
+        .map(|coin| (coin.denom.as_str(), coin.amount))
+        .chain(
+            tokens
+                .iter()
+                .map(|token| (token.address.as_str(), token.amount)),
+        );
+    for (denom, amount) in withdrawn {
+        WITHDRAWN.update(storage, denom, |withdrawn| -> StdResult<_> {
+            Ok(withdrawn.unwrap_or_default() + amount)
+        })?;
+    }
+
+    Ok(())
+}
+
+// the reverse of record_withdrawn, for withdrawals which were never sent
+fn unrecord_withdrawn(
+    storage: &mut dyn Storage,
+    coins: &[Coin],
+    tokens: &[Cw20Coin],
+) -> StdResult<()> {
+    let withdrawn = coins
+        .iter()
+        .map(|coin| (coin.denom.as_str(), coin.amount))
+        .chain(
+            tokens
+                .iter()
+                .map(|token| (token.address.as_str(), token.amount)),
+        );
+    for (denom, amount) in withdrawn {
+        WITHDRAWN.update(storage, denom, |withdrawn| -> StdResult<_> {
+            Ok(withdrawn.unwrap_or_default().saturating_sub(amount))
+        })?;
+    }
+
+    Ok(())
+}
+
+// balances of all the cw20 tokens the contract holds, queried from the token contracts
+// tokens queued for withdrawals or held for failed payouts are left out,
+// just like in the withdrawable balance
+fn cw20_balances(deps: Deps, env: &Env) -> StdResult<Vec<Cw20Coin>> {
+    let tokens = HELD_CW20
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut pending = PENDING_WITHDRAWALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, withdrawal)| withdrawal.cw20_funds))
+        .collect::<StdResult<Vec<_>>>()?;
+    for failed in FAILED_PAYOUTS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, failed) = failed?;
+        pending.push(failed.payout.cw20_funds);
+    }
+
+    let mut balances = vec![];
+    for token in tokens {
+        let resp: BalanceResponse = deps.querier.query_wasm_smart(
+            &token,
+            &Cw20QueryMsg::Balance {
+                address: env.contract.address.to_string(),
+            },
+        )?;
+        let queued: Uint128 = pending
+            .iter()
+            .flatten()
+            .filter(|queued| queued.address == token.as_str())
+            .map(|queued| queued.amount)
+            .sum();
+        let balance = resp.balance.saturating_sub(queued);
+        if !balance.is_zero() {
+            balances.push(Cw20Coin {
+                address: token.to_string(),
+                amount: balance,
+            });
+        }
+    }
+
+    Ok(balances)
+}
+
+// cw20 tokens are sent by executing the transfer on the token contract
+fn cw20_transfer(balance: &Cw20Coin, recipient: &str) -> StdResult<WasmMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: balance.address.clone(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_owned(),
+            amount: balance.amount,
+        })?,
+        funds: vec![],
+    })
+}
+
+// a payout holds either the coins of a bank send, or a single cw20 token
+fn payout_msg(payout: &Payout) -> StdResult<CosmosMsg> {
+    match payout.cw20_funds.first() {
+        Some(token) => Ok(cw20_transfer(token, payout.receiver.as_str())?.into()),
+        None => Ok(BankMsg::Send {
+            to_address: payout.receiver.to_string(),
+            amount: payout.funds.clone(),
+        }
+        .into()),
+    }
+}
+
+pub mod query {
+    use super::{
+        add_coin, available_balance, available_cw20_balance, cw20_balances, pending_unbonding,
+        staked_balance, withdrawable_balance,
+    };
+    use crate::msg::{
+        AcceptedCw20sResp, AcceptedDenomsResp, BadgeExtension, CampaignResp, ContributionResp,
+        CounterLimit, CounterResp, CountersResp, CountingPolicy, DonorResp, DonorsResp,
+        FailedPayoutResp, FailedPayoutsResp, LeaderboardEntry, LeaderboardResp, MatchingPoolResp,
+        MatchingPoolsResp, MessageResp, MessagesResp, NftInfoResp, OwnerOfResp, OwnershipResp,
+        PauseStatusResp, PayeeResp, PayeesResp, PayoutsResp, PendingChangeResp, PendingChangesResp,
+        PendingWithdrawalResp, PendingWithdrawalsResp, RefundMode, ReservesResp, Role,
+        RoleMembersResp, RolesResp, SortOrder, StakingStatusResp, StrictModeResp, Timelock,
+        TokensResp, ValueChangeResp, ValueHistoryResp, ValueResp, VestedDenom, VestedResp,
+        WithdrawableResp, WithdrawalLogEntryResp, WithdrawalLogResp,
+    };
+    use crate::state::{
+        leaderboard_rank, DonorTotals, MatchingPool, WithdrawalLogEntry, ACCEPTED_CW20, BADGES,
+        CAMPAIGNS, CONTRIBUTIONS, COUNTER, COUNTERS, COUNTER_HISTORY, COUNTER_LIMIT,
+        COUNTING_POLICY, DONORS, FAILED_PAYOUTS, LAST_CAMPAIGN_ID, LEADERBOARD, MATCHING_POOLS,
+        MESSAGES, MINIMAL_DONATIONS, OWNER, PAUSED, PAYEES, PAYOUTS, PENDING_CHANGES,
+        PENDING_OWNER, PENDING_WITHDRAWALS, REFUND_MODE, RESERVES, ROLES, STAKING, STRICT_MODE,
+        TIMELOCK, VESTING, WITHDRAWAL_LOG, WITHDRAWN,
+    };
+    use cosmwasm_std::{
+        coin, Addr, Deps, Env, Order, StdError, StdResult, Timestamp, Uint128, Uint64,
+    };
+    use cw20::Cw20Coin;
+    use cw_storage_plus::Bound;
+
+    // limits for the paginated queries, so a single query can't run out of gas
+    const DEFAULT_LIMIT: u32 = 10;
+    const MAX_LIMIT: u32 = 30;
+
+    // query::value() function will be called when QueryMsg::Value {} is received
+    // returns an arbitrary object which would be serialized before sending as a response
+    // pub fn value() -> ValueResp {
+    //     ValueResp { value: 0 }
+    // }
+    // loading a value from the state
+    // adding Deps argument to have access to contract storage
+    // returning an error in case loading from the state fails
+    // the returned value has to be wrapped in Ok(...)
+    pub fn value(deps: Deps, name: Option<String>) -> StdResult<ValueResp> {
+        // utilizing the load function to load from the state
+        // taking the state accessor as an argument
+        let value = match name {
+            Some(name) => COUNTERS.load(deps.storage, &name)?.value,
+            None => COUNTER.load(deps.storage)?,
+        };
+        Ok(ValueResp { value })
+    }
+
+    // the snapshot tells the value at the beginning of the block, which is the end of the previous one
+    pub fn value_at(deps: Deps, height: u64) -> StdResult<ValueResp> {
+        // the value at the end of the block is the one checkpointed at the next height
+        let next_height = Uint64::new(height).checked_add(Uint64::one())?;
+        let value = COUNTER
+            .may_load_at_height(deps.storage, next_height.u64())?
+            .ok_or_else(|| StdError::not_found("counter"))?;
+        Ok(ValueResp { value })
+    }
+
+    pub fn value_history(
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<ValueHistoryResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = start_after.map(Bound::exclusive);
+
+        let changes = COUNTER_HISTORY
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(change_id, change)| ValueChangeResp {
+                    change_id,
+                    value: change.value,
+                    height: change.height,
+                    time: change.time,
+                    cause: change.cause,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(ValueHistoryResp { changes })
+    }
+
+    pub fn counter_limit(deps: Deps) -> StdResult<Option<CounterLimit>> {
+        COUNTER_LIMIT.may_load(deps.storage)
+    }
+
+    pub fn counters(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CountersResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = start_after.as_deref().map(Bound::exclusive);
+
+        let counters = COUNTERS
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(name, counter)| CounterResp {
+                    name,
+                    value: counter.value,
+                    owner: counter.owner,
+                    minimal_donations: counter.minimal_donations,
+                    counting_policy: counter.counting_policy,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(CountersResp { counters })
+    }
+
+    pub fn ownership(deps: Deps) -> StdResult<OwnershipResp> {
+        // the owner is missing once the ownership has been renounced
+        let owner = OWNER.may_load(deps.storage)?;
+        let pending = PENDING_OWNER.may_load(deps.storage)?;
+
+        Ok(OwnershipResp {
+            owner,
+            pending_owner: pending.as_ref().map(|p| p.owner.clone()),
+            pending_expiry: pending.and_then(|p| p.expiry),
+        })
+    }
+
+    pub fn roles(deps: Deps, address: String) -> StdResult<RolesResp> {
+        let address = deps.api.addr_validate(&address)?;
+
+        // there are only a few roles, so checking all of them is cheaper than iterating the map
+        let roles = Role::ALL
+            .into_iter()
+            .filter(|role| ROLES.has(deps.storage, (role.as_str(), &address)))
+            .collect();
+
+        Ok(RolesResp { roles })
+    }
+
+    pub fn role_members(
+        deps: Deps,
+        role: Role,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<RoleMembersResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_after = start_after
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?;
+        let min = start_after.as_ref().map(Bound::exclusive);
+
+        let members = ROLES
+            .prefix(role.as_str())
+            .keys(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<_>>()?;
+
+        Ok(RoleMembersResp { members })
+    }
+
+    pub fn donor(deps: Deps, address: String) -> StdResult<DonorResp> {
+        let address = deps.api.addr_validate(&address)?;
+        // an address which never donated has just empty totals
+        let totals = DONORS.may_load(deps.storage, &address)?.unwrap_or_default();
+
+        Ok(donor_resp(address, totals))
+    }
+
+    pub fn donors(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<DonorsResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_after = start_after
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?;
+        let min = start_after.as_ref().map(Bound::exclusive);
+
+        let donors = DONORS
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(address, totals)| donor_resp(address, totals)))
+            .collect::<StdResult<_>>()?;
+
+        Ok(DonorsResp { donors })
+    }
+
+    fn donor_resp(address: Addr, totals: DonorTotals) -> DonorResp {
+        DonorResp {
+            address,
+            qualifying: totals.qualifying,
+            non_qualifying: totals.non_qualifying,
+            matched: totals.matched,
+        }
+    }
+
+    pub fn reserves(deps: Deps) -> StdResult<ReservesResp> {
+        let reserves = RESERVES
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(denom, amount)| coin(amount.u128(), denom)))
+            .collect::<StdResult<_>>()?;
+
+        Ok(ReservesResp { reserves })
+    }
+
+    pub fn withdrawable(deps: Deps, env: Env) -> StdResult<WithdrawableResp> {
+        Ok(WithdrawableResp {
+            funds: available_balance(deps, &env)?,
+            cw20_funds: available_cw20_balance(deps, &env)?,
+        })
+    }
+
+    // entries are logged in the order of time, so the time range is checked while iterating
+    pub fn withdrawal_log(
+        deps: Deps,
+        receiver: Option<String>,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<WithdrawalLogResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = start_after.map(Bound::exclusive);
+
+        let entries: Box<dyn Iterator<Item = StdResult<(u64, WithdrawalLogEntry)>>> = match receiver
+        {
+            Some(receiver) => {
+                let receiver = deps.api.addr_validate(&receiver)?;
+                Box::new(WITHDRAWAL_LOG.idx.receiver.prefix(receiver).range(
+                    deps.storage,
+                    min,
+                    None,
+                    Order::Ascending,
+                ))
+            }
+            None => Box::new(WITHDRAWAL_LOG.range(deps.storage, min, None, Order::Ascending)),
+        };
+
+        let entries = entries
+            .skip_while(
+                |item| matches!(item, Ok((_, entry)) if from.is_some_and(|from| entry.time < from)),
+            )
+            .take_while(
+                |item| !matches!(item, Ok((_, entry)) if to.is_some_and(|to| entry.time >= to)),
+            )
+            .take(limit)
+            .map(|item| {
+                item.map(|(log_id, entry)| WithdrawalLogEntryResp {
+                    log_id,
+                    executor: entry.executor,
+                    receiver: entry.receiver,
+                    funds: entry.funds,
+                    cw20_funds: entry.cw20_funds,
+                    height: entry.height,
+                    time: entry.time,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(WithdrawalLogResp { entries })
+    }
+
+    pub fn timelock(deps: Deps) -> StdResult<Option<Timelock>> {
+        TIMELOCK.may_load(deps.storage)
+    }
+
+    pub fn pending_withdrawals(
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<PendingWithdrawalsResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = start_after.map(Bound::exclusive);
+
+        let withdrawals = PENDING_WITHDRAWALS
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(withdrawal_id, withdrawal)| PendingWithdrawalResp {
+                    withdrawal_id,
+                    receiver: withdrawal.receiver,
+                    funds: withdrawal.funds,
+                    cw20_funds: withdrawal.cw20_funds,
+                    eta: withdrawal.eta,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(PendingWithdrawalsResp { withdrawals })
+    }
+
+    pub fn pending_changes(
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<PendingChangesResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = start_after.map(Bound::exclusive);
+
+        let changes = PENDING_CHANGES
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(change_id, pending)| PendingChangeResp {
+                    change_id,
+                    change: pending.change,
+                    eta: pending.eta,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(PendingChangesResp { changes })
+    }
+
+    pub fn failed_payouts(
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<FailedPayoutsResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = start_after.map(Bound::exclusive);
+
+        let payouts = FAILED_PAYOUTS
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(payout_id, failed)| FailedPayoutResp {
+                    payout_id,
+                    log_id: failed.payout.log_id,
+                    receiver: failed.payout.receiver,
+                    funds: failed.payout.funds,
+                    cw20_funds: failed.payout.cw20_funds,
+                    error: failed.error,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(FailedPayoutsResp { payouts })
+    }
+
+    pub fn staking_status(deps: Deps, env: Env) -> StdResult<StakingStatusResp> {
+        let staking = STAKING.may_load(deps.storage)?;
+        let delegation = match &staking {
+            Some(staking) => deps
+                .querier
+                .query_delegation(&env.contract.address, &staking.validator)?,
+            None => None,
+        };
+        let (bonded, rewards) = match delegation {
+            Some(delegation) => (vec![delegation.amount], delegation.accumulated_rewards),
+            None => (vec![], vec![]),
+        };
+
+        Ok(StakingStatusResp {
+            staking,
+            bonded,
+            unbonding: pending_unbonding(deps.storage, &env)?,
+            rewards,
+        })
+    }
+
+    pub fn vested(deps: Deps, env: Env) -> StdResult<VestedResp> {
+        let schedule = VESTING.may_load(deps.storage)?;
+
+        // denoms withdrawn in full are still listed, with nothing left in the balance
+        let mut balance = withdrawable_balance(deps, &env)?;
+        for token in cw20_balances(deps, &env)? {
+            add_coin(&mut balance, &coin(token.amount.u128(), token.address));
+        }
+        for denom in WITHDRAWN.keys(deps.storage, None, None, Order::Ascending) {
+            add_coin(&mut balance, &coin(0, denom?));
+        }
+        let staked = staked_balance(deps, &env)?;
+        for staked in &staked {
+            add_coin(&mut balance, &coin(0, &staked.denom));
+        }
+        balance.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+        let denoms = balance
+            .into_iter()
+            .map(|balance| {
+                let withdrawn = WITHDRAWN
+                    .may_load(deps.storage, &balance.denom)?
+                    .unwrap_or_default();
+                let staked = staked
+                    .iter()
+                    .find(|staked| staked.denom == balance.denom)
+                    .map_or(Uint128::zero(), |staked| staked.amount);
+                let total = balance.amount + withdrawn + staked;
+                let vested = schedule
+                    .as_ref()
+                    .map_or(total, |schedule| schedule.vested(total, env.block.time));
+                Ok(VestedDenom {
+                    denom: balance.denom,
+                    vested,
+                    withdrawn,
+                    locked: total - vested,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(VestedResp { schedule, denoms })
+    }
+
+    pub fn payees(deps: Deps) -> StdResult<PayeesResp> {
+        let payees = PAYEES
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|payee| {
+                let paid = PAYOUTS
+                    .may_load(deps.storage, &payee.address)?
+                    .unwrap_or_default();
+                Ok(PayeeResp {
+                    address: payee.address,
+                    share: payee.share,
+                    paid,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(PayeesResp { payees })
+    }
+
+    pub fn payouts(deps: Deps, address: String) -> StdResult<PayoutsResp> {
+        let address = deps.api.addr_validate(&address)?;
+        let paid = PAYOUTS
+            .may_load(deps.storage, &address)?
+            .unwrap_or_default();
+
+        Ok(PayoutsResp { paid })
+    }
+
+    pub fn matching_pool(deps: Deps, sponsor: String) -> StdResult<MatchingPoolResp> {
+        let sponsor = deps.api.addr_validate(&sponsor)?;
+        let pool = MATCHING_POOLS.load(deps.storage, &sponsor)?;
+
+        Ok(matching_pool_resp(sponsor, pool))
+    }
+
+    pub fn matching_pools(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<MatchingPoolsResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_after = start_after
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?;
+        let min = start_after.as_ref().map(Bound::exclusive);
+
+        let pools = MATCHING_POOLS
+            .range(deps.storage, min, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(sponsor, pool)| matching_pool_resp(sponsor, pool)))
+            .collect::<StdResult<_>>()?;
+
+        Ok(MatchingPoolsResp { pools })
+    }
+
+    fn matching_pool_resp(sponsor: Addr, pool: MatchingPool) -> MatchingPoolResp {
+        MatchingPoolResp {
+            sponsor,
+            remaining: pool.remaining,
+            ratio: pool.ratio,
+            cap: pool.cap,
+            matched: pool.matched,
+            deadline: pool.deadline,
+        }
+    }
+
+    pub fn accepted_denoms(deps: Deps) -> StdResult<AcceptedDenomsResp> {
+        // only a handful of denoms is expected, so they are all returned at once
+        let denoms = MINIMAL_DONATIONS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(denom, amount)| coin(amount.u128(), denom)))
+            .collect::<StdResult<_>>()?;
+
+        Ok(AcceptedDenomsResp { denoms })
+    }
+
+    pub fn accepted_cw20s(deps: Deps) -> StdResult<AcceptedCw20sResp> {
+        let tokens = ACCEPTED_CW20
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| {
+                item.map(|(address, amount)| Cw20Coin {
+                    address: address.to_string(),
+                    amount,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(AcceptedCw20sResp { tokens })
+    }
+
+    pub fn counting_policy(deps: Deps) -> StdResult<CountingPolicy> {
+        Ok(COUNTING_POLICY.may_load(deps.storage)?.unwrap_or_default())
+    }
+
+    pub fn refund_mode(deps: Deps) -> StdResult<RefundMode> {
+        Ok(REFUND_MODE.may_load(deps.storage)?.unwrap_or_default())
+    }
+
+    pub fn strict_mode(deps: Deps) -> StdResult<StrictModeResp> {
+        let strict = STRICT_MODE.may_load(deps.storage)?.unwrap_or_default();
+        Ok(StrictModeResp { strict })
+    }
+
+    pub fn pause_status(deps: Deps) -> StdResult<PauseStatusResp> {
+        let paused = PAUSED.may_load(deps.storage)?;
+
+        Ok(match paused {
+            Some(paused) => PauseStatusResp {
+                all: paused.all,
+                messages: paused.messages,
+            },
+            None => PauseStatusResp {
+                all: false,
+                messages: vec![],
+            },
+        })
+    }
+
+    pub fn campaign(deps: Deps, env: Env, campaign_id: Option<u64>) -> StdResult<CampaignResp> {
+        let campaign_id = match campaign_id {
+            Some(campaign_id) => campaign_id,
+            None => LAST_CAMPAIGN_ID.load(deps.storage)?,
+        };
+        let campaign = CAMPAIGNS.load(deps.storage, campaign_id)?;
+
+        Ok(CampaignResp {
+            campaign_id,
+            status: campaign.status(env.block.time),
+            goal: campaign.goal,
+            raised: campaign.raised,
+            deadline: campaign.deadline,
+        })
+    }
+
+    pub fn contribution(
+        deps: Deps,
+        campaign_id: u64,
+        address: String,
+    ) -> StdResult<ContributionResp> {
+        let address = deps.api.addr_validate(&address)?;
+        let contributed = CONTRIBUTIONS
+            .may_load(deps.storage, (&address, campaign_id))?
+            .unwrap_or_default();
+
+        Ok(ContributionResp { contributed })
+    }
+
+    pub fn messages(
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order: Option<SortOrder>,
+    ) -> StdResult<MessagesResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let order: Order = order.unwrap_or_default().into();
+        // when going backwards, the page starts below the last seen id
+        let (min, max) = match order {
+            Order::Ascending => (start_after.map(Bound::exclusive), None),
+            Order::Descending => (None, start_after.map(Bound::exclusive)),
+        };
+
+        let messages = MESSAGES
+            .range(deps.storage, min, max, order)
+            .take(limit)
+            .map(|item| {
+                item.map(|(id, message)| MessageResp {
+                    id,
+                    sender: message.sender,
+                    memo: message.memo,
+                    time: message.time,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(MessagesResp { messages })
+    }
+
+    pub fn leaderboard(
+        deps: Deps,
+        denom: String,
+        limit: Option<u32>,
+    ) -> StdResult<LeaderboardResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+        // the lowest rank belongs to the top donor
+        let entries = LEADERBOARD
+            .sub_prefix(&denom)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(rank, address)| LeaderboardEntry {
+                    address,
+                    amount: Uint128::new(leaderboard_rank(Uint128::new(rank))),
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(LeaderboardResp { entries })
+    }
+
+    // the token id of a badge is the address of its owner
+    pub fn owner_of(deps: Deps, token_id: String) -> StdResult<OwnerOfResp> {
+        let owner = deps.api.addr_validate(&token_id)?;
+        BADGES.load(deps.storage, &owner)?;
+
+        Ok(OwnerOfResp {
+            owner: owner.into_string(),
+            approvals: vec![],
+        })
+    }
+
+    pub fn nft_info(deps: Deps, token_id: String) -> StdResult<NftInfoResp> {
+        let owner = deps.api.addr_validate(&token_id)?;
+        let badge = BADGES.load(deps.storage, &owner)?;
+
+        Ok(NftInfoResp {
+            token_uri: None,
+            extension: BadgeExtension {
+                tier: badge.tier,
+                donated_units: badge.donated_units,
+            },
+        })
+    }
+
+    pub fn tokens(deps: Deps, owner: String, start_after: Option<String>) -> StdResult<TokensResp> {
+        let owner = deps.api.addr_validate(&owner)?;
+
+        // a donor owns a single badge at most, so pagination is only there for cw721 compatibility
+        let tokens = if start_after.is_none() && BADGES.has(deps.storage, &owner) {
+            vec![owner.into_string()]
+        } else {
+            vec![]
+        };
+
+        Ok(TokensResp { tokens })
+    }
+
+    // assignment lesson 3: add query to the contract
+    // taking a single number as its argument and returning the send argument incremented
+    /*
+    pub fn incremented(value: u64) -> ValueResp {
+        ValueResp { value: value + 1 }
+    }
+    */
+}
+
+/// creating a message handler for the execute entry point
+pub mod exec {
+    // use cosmwasm_std::{DepsMut, MessageInfo, Response, StdResult};
+    use cosmwasm_std::{
+        coin, from_json, to_json_binary, Addr, BankMsg, Coin, Decimal, Deps, DepsMut,
+        DistributionMsg, Env, Event, MessageInfo, Order, Response, StakingMsg, StdResult, Storage,
+        SubMsg, Timestamp, Uint128, WasmMsg,
+    };
+    use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg};
+    use cw_utils::{one_coin, Expiration};
+
+    use crate::{
+        error::ContractError,
+        msg::{
+            BadgeThresholds, BadgeTier, CampaignStatus, ConfigChange, CounterChangeCause,
+            CounterLimit, CountingPolicy, ExecMsg, Payee, ReceiveMsg, RefundMode, Role,
+            StakingConfig, Timelock, Unbonding,
+        },
+        state::{
+            leaderboard_rank, Badge, Campaign, ContributionCount, CounterState, DonationMessage,
+            MatchingPool, Paused, PayeeShare, Payout, PendingChange, PendingOwner,
+            PendingWithdrawal, RecentWithdrawal, WithdrawalLogEntry, ACCEPTED_CW20, BADGES,
+            BADGE_THRESHOLDS, CAMPAIGNS, CONTRIBUTIONS, CONTRIBUTION_COUNTS, COUNTER, COUNTERS,
+            COUNTER_LIMIT, COUNTING_POLICY, DONORS, ESCROW, FAILED_PAYOUTS, HELD_CW20,
+            LAST_CAMPAIGN_ID, LAST_CHANGE_ID, LAST_LOG_ID, LAST_MESSAGE_ID, LAST_PAYOUT_ID,
+            LAST_WITHDRAWAL_ID, LEADERBOARD, MATCHING_POOLS, MESSAGES, MINIMAL_DONATIONS, OWNER,
+            PAUSED, PAYEES, PAYOUTS, PENDING_CHANGES, PENDING_OWNER, PENDING_WITHDRAWALS,
+            RECENT_WITHDRAWALS, REFUND_MODE, RESERVES, ROLES, SENT_PAYOUTS, STAKING, STRICT_MODE,
+            TIMELOCK, UNBONDING, WITHDRAWAL_LOG,
+        },
+    };
+
+    // memos are kept short, so the message board stays cheap to store and to query
+    const MAX_MEMO_LENGTH: usize = 280;
+    // payee shares are in basis points
+    const TOTAL_SHARES: u32 = 10000;
+    use super::{
+        add_coin, available_balance, available_cw20_balance, coins_to_string, counted_amount,
+        counter_increment, limit_counter, payout_msg, pending_unbonding, record_withdrawn,
+        save_accepted_cw20, save_counter, save_minimal_donation, sub_coin, unrecord_withdrawn,
+        validate_counting_policy, withdrawable_balance,
+    };
+
+    // every owner-only handler goes through this check
+    // the owner is loaded on every call, so it always follows the current owner
+    fn ensure_owner(deps: Deps, sender: &Addr) -> Result<Addr, ContractError> {
+        let owner = OWNER
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoOwner {})?;
+
+        if *sender != owner {
+            // return Err(StdError::generic_err("Unauthorized"));
+            return Err(ContractError::Unauthorized {
+                owner: owner.to_string(),
+            });
+        }
+
+        Ok(owner)
+    }
+
+    // in the strict mode funds can only be sent with messages which expect them
+    pub fn ensure_no_funds(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+        if !info.funds.is_empty() && STRICT_MODE.may_load(deps.storage)?.unwrap_or_default() {
+            return Err(ContractError::UnexpectedFunds {});
+        }
+
+        Ok(())
+    }
+
+    // paused messages are rejected before they are handled
+    pub fn ensure_not_paused(deps: Deps, msg: &ExecMsg) -> Result<(), ContractError> {
+        let Some(paused) = PAUSED.may_load(deps.storage)? else {
+            return Ok(());
+        };
+
+        let message = msg.name();
+        if !ExecMsg::PAUSABLE.contains(&message) {
+            return Ok(());
+        }
+        if paused.all || paused.messages.iter().any(|paused| paused == message) {
+            return Err(ContractError::Paused {
+                message: message.to_owned(),
+            });
+        }
+
+        Ok(())
+    }
+
+    // while a campaign is open, donations of its goal denoms are escrowed for it
+    // returns the campaign id if anything was escrowed
+    fn escrow_for_campaign(
+        deps: DepsMut,
+        env: &Env,
+        donor: &Addr,
+        donation: &Donation,
+        name: Option<&str>,
+    ) -> Result<Option<u64>, ContractError> {
+        let donated = &donation.kept_qualifying;
+        let Some(campaign_id) = LAST_CAMPAIGN_ID.may_load(deps.storage)? else {
+            return Ok(None);
+        };
+        let mut campaign = CAMPAIGNS.load(deps.storage, campaign_id)?;
+        if campaign.status(env.block.time) != CampaignStatus::Open {
+            return Ok(None);
+        }
+
+        let contributed: Vec<_> = donated
+            .iter()
+            .filter(|coin| campaign.goal.iter().any(|goal| goal.denom == coin.denom))
+            .collect();
+        if contributed.is_empty() {
+            return Ok(None);
+        }
+
+        let mut escrow = ESCROW.may_load(deps.storage)?.unwrap_or_default();
+        let mut contribution = CONTRIBUTIONS
+            .may_load(deps.storage, (donor, campaign_id))?
+            .unwrap_or_default();
+        for coin in &contributed {
+            add_coin(&mut campaign.raised, coin);
+            add_coin(&mut escrow, coin);
+            add_coin(&mut contribution, coin);
+        }
+        CAMPAIGNS.save(deps.storage, campaign_id, &campaign)?;
+        ESCROW.save(deps.storage, &escrow)?;
+        CONTRIBUTIONS.save(deps.storage, (donor, campaign_id), &contribution)?;
+
+        // the contribution counted for what the donation wouldn't have counted without it
+        let minimal = |coin: &Coin| {
+            donation
+                .minimals
+                .iter()
+                .find(|minimal| minimal.denom == coin.denom)
+                .map_or(Uint128::one(), |minimal| minimal.amount)
+        };
+        let remaining: Vec<_> = donated
+            .iter()
+            .filter(|coin| !campaign.goal.iter().any(|goal| goal.denom == coin.denom))
+            .map(|coin| (coin, minimal(coin)))
+            .collect();
+        let increment = donation
+            .increment
+            .saturating_sub(counter_increment(&donation.policy, &remaining)?);
+
+        let mut count = CONTRIBUTION_COUNTS
+            .may_load(deps.storage, (donor, campaign_id))?
+            .unwrap_or_default();
+        for coin in contributed {
+            count.badge_units += coin.amount / minimal(coin);
+        }
+        match name {
+            Some(name) => match count.named.iter_mut().find(|(named, _)| named == name) {
+                Some((_, counted)) => *counted += increment,
+                None => count.named.push((name.to_owned(), increment)),
+            },
+            None => count.counter += increment,
+        }
+        CONTRIBUTION_COUNTS.save(deps.storage, (donor, campaign_id), &count)?;
+
+        Ok(Some(campaign_id))
+    }
+
+    // takes back everything a refunded contribution added to the counters, the ledger and the badge
+    fn unrecord_contribution(
+        deps: DepsMut,
+        env: &Env,
+        donor: &Addr,
+        contributed: &[Coin],
+        count: ContributionCount,
+    ) -> StdResult<()> {
+        if !count.counter.is_zero() {
+            let counter = COUNTER.load(deps.storage)?.saturating_sub(count.counter);
+            save_counter(deps.storage, env, counter, CounterChangeCause::Refund)?;
+        }
+        for (name, counted) in count.named {
+            // the counter could be removed since, then there is nothing to take back
+            if let Some(mut named) = COUNTERS.may_load(deps.storage, &name)? {
+                named.value = named.value.saturating_sub(counted);
+                COUNTERS.save(deps.storage, &name, &named)?;
+            }
+        }
+
+        let mut totals = DONORS.may_load(deps.storage, donor)?.unwrap_or_default();
+        for coin in contributed {
+            let previous = totals
+                .qualifying
+                .iter()
+                .find(|c| c.denom == coin.denom)
+                .map_or(Uint128::zero(), |c| c.amount);
+            sub_coin(&mut totals.qualifying, coin);
+
+            // the donor moves down the leaderboard, or leaves it with nothing left donated
+            LEADERBOARD.remove(
+                deps.storage,
+                (&coin.denom, leaderboard_rank(previous), donor),
+            );
+            let current = previous.saturating_sub(coin.amount);
+            if !current.is_zero() {
+                LEADERBOARD.save(
+                    deps.storage,
+                    (&coin.denom, leaderboard_rank(current), donor),
+                    &(),
+                )?;
+            }
+        }
+        DONORS.save(deps.storage, donor, &totals)?;
+
+        if let Some(badge) = BADGES.may_load(deps.storage, donor)? {
+            let donated_units = badge.donated_units.saturating_sub(count.badge_units);
+            if donated_units.is_zero() {
+                BADGES.remove(deps.storage, donor);
+            } else {
+                let thresholds = BADGE_THRESHOLDS.may_load(deps.storage)?.unwrap_or_default();
+                let tier = badge_tier(&thresholds, donated_units);
+                BADGES.save(
+                    deps.storage,
+                    donor,
+                    &Badge {
+                        tier,
+                        donated_units,
+                    },
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn badge_tier(thresholds: &BadgeThresholds, donated_units: Uint128) -> BadgeTier {
+        if donated_units >= thresholds.gold {
+            BadgeTier::Gold
+        } else if donated_units >= thresholds.silver {
+            BadgeTier::Silver
+        } else {
+            BadgeTier::Bronze
+        }
+    }
+
+    // mints the donor badge on the first qualifying donation and upgrades its tier later on
+    // returns the badge tier if the badge was minted or upgraded
+    fn award_badge(deps: DepsMut, donor: &Addr, units: Uint128) -> StdResult<Option<BadgeTier>> {
+        let thresholds = BADGE_THRESHOLDS.may_load(deps.storage)?.unwrap_or_default();
+        let previous = BADGES.may_load(deps.storage, donor)?;
+
+        let donated_units = previous
+            .as_ref()
+            .map_or(Uint128::zero(), |badge| badge.donated_units)
+            + units;
+        let tier = badge_tier(&thresholds, donated_units);
+        BADGES.save(
+            deps.storage,
+            donor,
+            &Badge {
+                tier,
+                donated_units,
+            },
+        )?;
+
+        match previous {
+            Some(badge) if badge.tier == tier => Ok(None),
+            _ => Ok(Some(tier)),
+        }
+    }
+
+    // the shared guard for all privileged handlers
+    // the owner can do everything, other addresses need the role granted
+    fn ensure_role(deps: Deps, sender: &Addr, role: Role) -> Result<(), ContractError> {
+        if ROLES.has(deps.storage, (role.as_str(), sender)) {
+            return Ok(());
+        }
+
+        match ensure_owner(deps, sender) {
+            Ok(_) => Ok(()),
+            // without an owner the missing role is the only reason for rejection
+            Err(ContractError::NoOwner {}) => Err(ContractError::MissingRole {
+                role: role.as_str().to_owned(),
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    // adding the MessageInfo to the update function
+    // -> MessageInfo contains additional metadata about the sent message
+    // (message sender and the funds sent)
+    // replacing poke with donate functionality
+    // pub fn poke(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
+    pub fn donate(
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        memo: Option<String>,
+        name: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // similar to instantiate, but instead of just storing value in the COUNTER
+        // the update function is used to update the underlying value
+
+        // the update function takes the (1) borrow to the storage object
+        // and then (2) the closure, which would be executed on the underlying object
+
+        // the value returned by the closure should be a Result
+        // with the type stored as a COUNTER in an Ok variant
+        // the Err variant can be anything implementing From<cosmwasm_std::StdError>
+
+        // Rust has to know what type it should use because the error type is never used here
+        // the type hint for the type returned from closure has to be provided
+        /*
+        COUNTER.update(
+            deps.storage,
+            |counter| -> StdResult<_> { Ok(counter + 1) }
+        )?;
+        */
+
+        // splitting updating the counter to keep the new counter value for further usage
+
+        // let counter = COUNTER.load(deps.storage)? + 1;
+        // COUNTER.save(deps.storage, &counter)?;
+
+        // we're having minimal donations you want to count
+        // now we want to iterate through all the funds sent to the contract
+        // and sort out the ones which are of an accepted denom, and minimal amount
+        // funds sent with the message can be addressed using the funds field of the info argument
+        // a named counter has minimal donations of its own
+        let named = match &name {
+            Some(name) => Some((name.as_str(), load_counter(deps.storage, name)?)),
+            None => None,
+        };
+        let mut funds = vec![];
+        for coin in info.funds {
+            // to filter interesting donations
+            // you first need to load a minimal donation of the coin denom from the state
+            let minimal = match &named {
+                Some((_, named)) => named.minimal_donation(&coin.denom),
+                None => MINIMAL_DONATIONS.may_load(deps.storage, &coin.denom)?,
+            };
+            funds.push((coin, minimal));
+        }
+
+        let donation = record_donation(deps.branch(), &env, &info.sender, funds, memo, named)?;
+        let matched = match_donation(deps.branch(), &env, &info.sender, &donation.kept_qualifying)?;
+        let campaign_id =
+            escrow_for_campaign(deps, &env, &info.sender, &donation, name.as_deref())?;
+
+        // every execution emits events (logs reporting what was perfromed by an action)
+        // an event contains a type and the set of key-value pairs named attributes
+
+        // events are emitted from execution using the Response::add_event function
+        // passing the constructed Event type
+
+        // every execution emits at least one default event
+        // to add attributes to the wasm event we can use a Response::add_attribute function
+        // adding three attributes to Response object: action, sender & counter
+        let mut resp = Response::new()
+            .add_attribute("action", "poke")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("counter", donation.counter.to_string());
+
+        if let Some(name) = name {
+            resp = resp.add_attribute("name", name);
+        }
+        if let Some(campaign_id) = campaign_id {
+            resp = resp.add_attribute("campaign_id", campaign_id.to_string());
+        }
+        if !matched.is_empty() {
+            resp = resp.add_attribute("matched", coins_to_string(&matched));
+        }
+        resp = donation_attributes(resp, &donation);
+        if !donation.refunded.is_empty() {
+            resp = resp.add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: donation.refunded,
+            });
+        }
+
+        // Ok(Response::new())
+        Ok(resp)
+    }
+
+    // cw20 tokens are not sent along with the message like native funds
+    // the token contract calls this handler after moving the tokens to the contract,
+    // so the donor is the sender of the wrapped message, not of the message itself
+    pub fn receive(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: Cw20ReceiveMsg,
+    ) -> Result<Response, ContractError> {
+        match from_json(&msg.msg)? {
+            ReceiveMsg::Donate { memo, name } => {
+                donate_cw20(deps, env, info.sender, msg, memo, name)
+            }
+        }
+    }
+
+    fn donate_cw20(
+        mut deps: DepsMut,
+        env: Env,
+        token: Addr,
+        msg: Cw20ReceiveMsg,
+        memo: Option<String>,
+        name: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // tokens which are not accepted are always rejected, so they don't get stuck in the contract
+        let minimal = ACCEPTED_CW20
+            .may_load(deps.storage, &token)?
+            .ok_or_else(|| ContractError::InvalidDenom {
+                denom: token.to_string(),
+            })?;
+        let donor = deps.api.addr_validate(&msg.sender)?;
+
+        // a named counter has minimal donations of its own, with the token address as the denom
+        let named = match &name {
+            Some(name) => Some((name.as_str(), load_counter(deps.storage, name)?)),
+            None => None,
+        };
+        let minimal = match &named {
+            Some((_, named)) => named.minimal_donation(token.as_str()),
+            None => Some(minimal),
+        };
+
+        let funds = vec![(coin(msg.amount.u128(), token.as_str()), minimal)];
+        let donation = record_donation(deps.branch(), &env, &donor, funds, memo, named)?;
+        HELD_CW20.save(deps.storage, &token, &())?;
+
+        // cw20 donations are not escrowed for campaigns, as campaign goals are in native denoms
+        let mut resp = Response::new()
+            .add_attribute("action", "donate_cw20")
+            .add_attribute("sender", donor.as_str())
+            .add_attribute("token", token.as_str())
+            .add_attribute("counter", donation.counter.to_string());
+
+        resp = donation_attributes(resp, &donation);
+        for refunded in &donation.refunded {
+            resp = resp.add_message(WasmMsg::Execute {
+                contract_addr: token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: donor.to_string(),
+                    amount: refunded.amount,
+                })?,
+                funds: vec![],
+            });
+        }
+
+        Ok(resp)
+    }
+
+    // matches the qualifying donation from all active pools of its denoms
+    // the matched funds are already held by the contract, so they only move out of the pools
+    // returns what was matched in total
+    fn match_donation(
+        deps: DepsMut,
+        env: &Env,
+        donor: &Addr,
+        donated: &[Coin],
+    ) -> StdResult<Vec<Coin>> {
+        if donated.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let pools = MATCHING_POOLS
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let mut matched = vec![];
+        for (sponsor, mut pool) in pools {
+            let Some(coin) = donated.iter().find(|c| c.denom == pool.remaining.denom) else {
+                continue;
+            };
+            let amount = pool.match_amount(coin.amount, env.block.time);
+            if amount.is_zero() {
+                continue;
+            }
+
+            pool.remaining.amount -= amount;
+            pool.matched += amount;
+            MATCHING_POOLS.save(deps.storage, &sponsor, &pool)?;
+            add_coin(&mut matched, &Coin::new(amount.u128(), &coin.denom));
+        }
+
+        if !matched.is_empty() {
+            let mut totals = DONORS.may_load(deps.storage, donor)?.unwrap_or_default();
+            for coin in &matched {
+                add_coin(&mut totals.matched, coin);
+            }
+            DONORS.save(deps.storage, donor, &totals)?;
+        }
+
+        Ok(matched)
+    }
+
+    // the outcome of a donation, no matter if it was made with native or cw20 funds
+    struct Donation {
+        counter: Uint128,
+        // what the counter actually grew by, and how it was counted
+        increment: Uint128,
+        policy: CountingPolicy,
+        // the minimal donation of every kept qualifying denom
+        minimals: Vec<Coin>,
+        kept_qualifying: Vec<Coin>,
+        kept_non_qualifying: Vec<Coin>,
+        refunded: Vec<Coin>,
+        message_id: Option<u64>,
+        // the memo sent with a donation which didn't qualify, so it wasn't posted
+        memo_dropped: bool,
+        badge_tier: Option<BadgeTier>,
+    }
+
+    // counts the donated funds, and records them in the donor ledger, message board and badges
+    // every coin comes with the minimal donation of its denom, if the denom is accepted at all
+    // the donation is counted by the named counter, or by the default one
+    fn record_donation(
+        mut deps: DepsMut,
+        env: &Env,
+        donor: &Addr,
+        funds: Vec<(Coin, Option<Uint128>)>,
+        memo: Option<String>,
+        // the named counter is loaded by the caller already, together with its name
+        mut named: Option<(&str, CounterState)>,
+    ) -> Result<Donation, ContractError> {
+        let mut counter = match &named {
+            Some((_, named)) => named.value,
+            None => COUNTER.load(deps.storage)?,
+        };
+        let previous_counter = counter;
+
+        if memo
+            .as_ref()
+            .is_some_and(|memo| memo.chars().count() > MAX_MEMO_LENGTH)
+        {
+            return Err(ContractError::MemoTooLong {
+                max: MAX_MEMO_LENGTH,
+            });
+        }
+
+        // in the strict mode every coin has to count, otherwise the whole donation fails
+        let strict = STRICT_MODE.may_load(deps.storage)?.unwrap_or_default();
+        if strict && funds.is_empty() {
+            return Err(ContractError::NoFunds {});
+        }
+
+        let mut qualifying = vec![];
+        let mut non_qualifying = vec![];
+        for (coin, minimal) in &funds {
+            match *minimal {
+                Some(minimal) if coin.amount >= minimal => qualifying.push((coin, minimal)),
+                Some(minimal) if strict => {
+                    return Err(ContractError::BelowMinimum {
+                        required: Coin::new(minimal.u128(), &coin.denom),
+                        sent: coin.clone(),
+                    })
+                }
+                None if strict => {
+                    return Err(ContractError::InvalidDenom {
+                        denom: coin.denom.clone(),
+                    })
+                }
+                _ => non_qualifying.push(coin),
+            }
+        }
+
+        // the counting policy decides how much the qualifying coins are worth
+        let policy = match &named {
+            Some((_, named)) => named.counting_policy.clone(),
+            None => COUNTING_POLICY.may_load(deps.storage)?.unwrap_or_default(),
+        };
+        let increment = counter_increment(&policy, &qualifying)?;
+        if !increment.is_zero() {
+            // not saving a counter if it was not incremented to save gas
+            counter = limit_counter(deps.storage, counter.checked_add(increment)?)?;
+            match &mut named {
+                Some((name, named)) => {
+                    named.value = counter;
+                    COUNTERS.save(deps.storage, name, named)?;
+                }
+                None => save_counter(deps.storage, env, counter, CounterChangeCause::Donate)?,
+            }
+        }
+
+        // depending on the refund mode, the funds which don't count are sent back
+        let refund_mode = REFUND_MODE.may_load(deps.storage)?.unwrap_or_default();
+        let mut kept_qualifying = vec![];
+        let mut kept_non_qualifying = vec![];
+        let mut refunded = vec![];
+        let mut donated_units = Uint128::zero();
+        let mut minimals = vec![];
+        for (qualifying_coin, minimal) in &qualifying {
+            let kept = match refund_mode {
+                RefundMode::NonQualifyingAndExcess => {
+                    counted_amount(&policy, qualifying_coin, *minimal)?
+                }
+                _ => qualifying_coin.amount,
+            };
+            let excess = qualifying_coin.amount - kept;
+            donated_units += kept / *minimal;
+
+            kept_qualifying.push(coin(kept.u128(), &qualifying_coin.denom));
+            minimals.push(coin(minimal.u128(), &qualifying_coin.denom));
+            if !excess.is_zero() {
+                refunded.push(coin(excess.u128(), &qualifying_coin.denom));
+            }
+        }
+        for non_qualifying_coin in non_qualifying {
+            match refund_mode {
+                RefundMode::Disabled => kept_non_qualifying.push(non_qualifying_coin.clone()),
+                _ => refunded.push(non_qualifying_coin.clone()),
+            }
+        }
+
+        // keeping track of what every donor gave, split by whether it counted or not
+        // refunded funds were never really donated, so they are not recorded
+        if !kept_qualifying.is_empty() || !kept_non_qualifying.is_empty() {
+            let mut totals = DONORS.may_load(deps.storage, donor)?.unwrap_or_default();
+            for coin in &kept_qualifying {
+                let previous = totals
+                    .qualifying
+                    .iter()
+                    .find(|c| c.denom == coin.denom)
+                    .map_or(Uint128::zero(), |c| c.amount);
+                add_coin(&mut totals.qualifying, coin);
+
+                // moving the donor to the new position on the leaderboard
+                if !previous.is_zero() {
+                    LEADERBOARD.remove(
+                        deps.storage,
+                        (&coin.denom, leaderboard_rank(previous), donor),
+                    );
+                }
+                let current = previous + coin.amount;
+                LEADERBOARD.save(
+                    deps.storage,
+                    (&coin.denom, leaderboard_rank(current), donor),
+                    &(),
+                )?;
+            }
+            for coin in &kept_non_qualifying {
+                add_coin(&mut totals.non_qualifying, coin);
+            }
+            DONORS.save(deps.storage, donor, &totals)?;
+        }
+
+        // memos are only posted along with qualifying donations, so the board can't be spammed for free
+        let memo_dropped = memo.is_some() && kept_qualifying.is_empty();
+        let message_id = match memo {
+            Some(memo) if !kept_qualifying.is_empty() => {
+                let message_id = LAST_MESSAGE_ID.may_load(deps.storage)?.unwrap_or_default() + 1;
+                let message = DonationMessage {
+                    sender: donor.clone(),
+                    memo,
+                    time: env.block.time,
+                };
+                MESSAGES.save(deps.storage, message_id, &message)?;
+                LAST_MESSAGE_ID.save(deps.storage, &message_id)?;
+                Some(message_id)
+            }
+            _ => None,
+        };
+
+        let badge_tier = if kept_qualifying.is_empty() {
+            None
+        } else {
+            award_badge(deps.branch(), donor, donated_units)?
+        };
+
+        Ok(Donation {
+            counter,
+            increment: counter.saturating_sub(previous_counter),
+            policy,
+            minimals,
+            kept_qualifying,
+            kept_non_qualifying,
+            refunded,
+            message_id,
+            memo_dropped,
+            badge_tier,
+        })
+    }
+
+    fn donation_attributes(mut resp: Response, donation: &Donation) -> Response {
+        if let Some(message_id) = donation.message_id {
+            resp = resp.add_attribute("message_id", message_id.to_string());
+        }
+        if donation.memo_dropped {
+            resp = resp.add_attribute("memo_dropped", "true");
+        }
+        if let Some(badge_tier) = donation.badge_tier {
+            resp = resp.add_attribute("badge_tier", badge_tier.as_str());
+        }
+
+        // attributes can't be empty, so they are only added when there is something to list
+        let kept: Vec<_> = donation
+            .kept_qualifying
+            .iter()
+            .chain(&donation.kept_non_qualifying)
+            .cloned()
+            .collect();
+        if !kept.is_empty() {
+            resp = resp.add_attribute("kept", coins_to_string(&kept));
+        }
+        if !donation.refunded.is_empty() {
+            resp = resp.add_attribute("refunded", coins_to_string(&donation.refunded));
+        }
+
+        resp
+    }
+
+    // assignment lesson 6: adding another execution message
+    // which resets an internal counter (setting it to given value)
+    pub fn reset(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        counter: Uint128,
+        name: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // resetting above the limit is limited just like the donations
+        let counter = limit_counter(deps.storage, counter)?;
+        let mut resp = Response::new()
+            .add_attribute("action", "reset")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("counter", counter.to_string());
+
+        match name {
+            // named counters are reset by their owners, the roles only cover the default counter
+            Some(name) => {
+                let mut named = load_counter(deps.storage, &name)?;
+                if info.sender != named.owner {
+                    return Err(ContractError::Unauthorized {
+                        owner: named.owner.into_string(),
+                    });
+                }
+                named.value = counter;
+                COUNTERS.save(deps.storage, &name, &named)?;
+                resp = resp.add_attribute("name", name);
+            }
+            None => {
+                ensure_role(deps.as_ref(), &info.sender, Role::Resetter)?;
+                save_counter(deps.storage, &env, counter, CounterChangeCause::Reset)?;
+            }
+        }
+
+        Ok(resp)
+    }
+
+    fn load_counter(storage: &dyn Storage, name: &str) -> Result<CounterState, ContractError> {
+        COUNTERS
+            .may_load(storage, name)?
+            .ok_or_else(|| ContractError::CounterNotFound {
+                name: name.to_owned(),
+            })
+    }
+
+    pub fn set_counter_limit(
+        deps: DepsMut,
+        info: MessageInfo,
+        limit: Option<CounterLimit>,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+
+        let mut resp = Response::new()
+            .add_attribute("action", "set_counter_limit")
+            .add_attribute("sender", info.sender.as_str());
+
+        match limit {
+            Some(limit) => {
+                resp = resp.add_attribute("max", limit.max.to_string());
+                COUNTER_LIMIT.save(deps.storage, &limit)?;
+            }
+            None => COUNTER_LIMIT.remove(deps.storage),
+        }
+
+        Ok(resp)
+    }
+
+    pub fn create_counter(
+        deps: DepsMut,
+        info: MessageInfo,
+        name: String,
+        minimal_donations: Vec<Coin>,
+        counting_policy: CountingPolicy,
+        owner: Option<String>,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        if COUNTERS.has(deps.storage, &name) {
+            return Err(ContractError::CounterExists { name });
+        }
+        // the same rules as for the default counter
+        if let Some(minimal) = minimal_donations
+            .iter()
+            .find(|minimal| minimal.amount.is_zero())
+        {
+            return Err(ContractError::InvalidMinimalDonation {
+                denom: minimal.denom.clone(),
+            });
+        }
+        validate_counting_policy(&counting_policy)?;
+
+        let owner = match owner {
+            Some(owner) => deps.api.addr_validate(&owner)?,
+            None => info.sender.clone(),
+        };
+        let resp = Response::new()
+            .add_attribute("action", "create_counter")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("name", &name)
+            .add_attribute("owner", owner.as_str());
+
+        let counter = CounterState {
+            value: Uint128::zero(),
+            owner,
+            minimal_donations,
+            counting_policy,
+        };
+        COUNTERS.save(deps.storage, &name, &counter)?;
+
+        Ok(resp)
+    }
+
+    // handler for the execution message variant
+    pub fn withdraw(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+        // we need to check if the message sender is the current owner of the contract
+        // or was granted the withdrawer role
+        // if not, we immediately fail execution with our custom Unauthorized error
+        ensure_role(deps.as_ref(), &info.sender, Role::Withdrawer)?;
+
+        // then we need to figure out how much funds to send to the contract owner
+        // because we want to send all the funds we query the blockchain for its state
+        // via a a querier object on the deps argument
+        // to get the contract's address we use the env entry point argument
+        // it contains all relevant meta information like the currently executed contract address
+        // funds escrowed for campaigns are left out of it, and so are funds which are not vested yet
+        // staked funds have to be unbonded first, the completed unbondings are in the balance again
+        release_unbonded(deps.storage, &env)?;
+        let balance = available_balance(deps.as_ref(), &env)?;
+        // cw20 balances have to be queried from every token contract separately
+        let cw20_balance = available_cw20_balance(deps.as_ref(), &env)?;
+        record_withdrawn(deps.storage, &balance, &cw20_balance)?;
+
+        // we can add the Send variant of a BankMsg to the Response using the add_message method
+        let resp = Response::new()
+            .add_attribute("action", "withdraw")
+            .add_attribute("sender", info.sender.as_str());
+
+        // large withdrawals wait in the queue, the rest is sent right away
+        let receiver = info.sender.clone();
+        send_or_queue(
+            deps,
+            &env,
+            &info.sender,
+            resp,
+            receiver,
+            balance,
+            cw20_balance,
+            false,
+        )
+    }
+
+    // preparing the message for the blockchain: the message we are looking for is a BankMsg
+    // particularly the Send variant of a BankMsg
+    // it takes a funds receiver and amount
+    // sending nothing would fail, so the message is only added if there is anything to send
+    // every withdrawal which was sent is logged
+    // the messages are sent as submessages replied to always, so a receiver rejecting the funds
+    // doesn't abort the withdrawal - the reply records the failed payout to be retried instead
+    fn send_funds(
+        storage: &mut dyn Storage,
+        env: &Env,
+        executor: &Addr,
+        mut resp: Response,
+        receiver: Addr,
+        funds: Vec<Coin>,
+        cw20_funds: Vec<Cw20Coin>,
+    ) -> StdResult<Response> {
+        if funds.is_empty() && cw20_funds.is_empty() {
+            return Ok(resp);
+        }
+
+        let log_id = LAST_LOG_ID.may_load(storage)?.unwrap_or_default() + 1;
+
+        let mut payouts = vec![];
+        if !funds.is_empty() {
+            payouts.push(Payout {
+                log_id,
+                receiver: receiver.clone(),
+                funds: funds.clone(),
+                cw20_funds: vec![],
+            });
+        }
+        for token in &cw20_funds {
+            payouts.push(Payout {
+                log_id,
+                receiver: receiver.clone(),
+                funds: vec![],
+                cw20_funds: vec![token.clone()],
+            });
+        }
+        for payout in payouts {
+            let payout_id = LAST_PAYOUT_ID.may_load(storage)?.unwrap_or_default() + 1;
+            resp = resp.add_submessage(SubMsg::reply_always(payout_msg(&payout)?, payout_id));
+            SENT_PAYOUTS.save(storage, payout_id, &payout)?;
+            LAST_PAYOUT_ID.save(storage, &payout_id)?;
+        }
+        let entry = WithdrawalLogEntry {
+            executor: executor.clone(),
+            receiver,
+            funds,
+            cw20_funds,
+            height: env.block.height,
+            time: env.block.time,
+        };
+        WITHDRAWAL_LOG.save(storage, log_id, &entry)?;
+        LAST_LOG_ID.save(storage, &log_id)?;
+
+        Ok(resp.add_attribute("log_id", log_id.to_string()))
+    }
+
+    // withdrawals with any denom above its timelock threshold are queued as a whole
+    // the threshold limits everything sent right away within the delay, not a single withdrawal,
+    // so a series of small withdrawals is queued as soon as their total gets above it
+    // returns the delay to wait for, the withdrawal sent right away is remembered instead
+    fn withdrawal_delay(
+        storage: &mut dyn Storage,
+        env: &Env,
+        funds: &[Coin],
+        cw20_funds: &[Cw20Coin],
+    ) -> StdResult<Option<u64>> {
+        let Some(timelock) = TIMELOCK.may_load(storage)? else {
+            return Ok(None);
+        };
+
+        let mut recent = RECENT_WITHDRAWALS.may_load(storage)?.unwrap_or_default();
+        recent.retain(|withdrawal| withdrawal.time.plus_seconds(timelock.delay) > env.block.time);
+
+        let mut withdrawn = funds.to_vec();
+        for token in cw20_funds {
+            add_coin(
+                &mut withdrawn,
+                &Coin::new(token.amount.u128(), &token.address),
+            );
+        }
+        let mut total = withdrawn.clone();
+        for coin in recent.iter().flat_map(|withdrawal| &withdrawal.funds) {
+            add_coin(&mut total, coin);
+        }
+
+        let locked = total.iter().any(|coin| {
+            timelock
+                .thresholds
+                .iter()
+                .any(|threshold| threshold.denom == coin.denom && coin.amount > threshold.amount)
+        });
+        if locked {
+            return Ok(Some(timelock.delay));
+        }
+
+        recent.push(RecentWithdrawal {
+            funds: withdrawn,
+            time: env.block.time,
+        });
+        RECENT_WITHDRAWALS.save(storage, &recent)?;
+
+        Ok(None)
+    }
+
+    fn queue_withdrawal(
+        storage: &mut dyn Storage,
+        resp: Response,
+        withdrawal: PendingWithdrawal,
+    ) -> StdResult<Response> {
+        let withdrawal_id = LAST_WITHDRAWAL_ID.may_load(storage)?.unwrap_or_default() + 1;
+        PENDING_WITHDRAWALS.save(storage, withdrawal_id, &withdrawal)?;
+        LAST_WITHDRAWAL_ID.save(storage, &withdrawal_id)?;
+
+        let resp = resp
+            .add_attribute("withdrawal_id", withdrawal_id.to_string())
+            .add_attribute("eta", withdrawal.eta.to_string());
+
+        Ok(resp)
+    }
+
+    // cw20 payouts are recorded with the token address as the denom, like donations
+    fn record_payout(
+        storage: &mut dyn Storage,
+        payee: &Addr,
+        funds: &[Coin],
+        cw20_funds: &[Cw20Coin],
+    ) -> StdResult<()> {
+        let mut paid = PAYOUTS.may_load(storage, payee)?.unwrap_or_default();
+        for coin in funds {
+            add_coin(&mut paid, coin);
+        }
+        for token in cw20_funds {
+            add_coin(&mut paid, &Coin::new(token.amount.u128(), &token.address));
+        }
+        PAYOUTS.save(storage, payee, &paid)
+    }
+
+    // the payouts are only recorded when they are sent, a queued one waits for its execution
+    #[allow(clippy::too_many_arguments)]
+    fn send_or_queue(
+        deps: DepsMut,
+        env: &Env,
+        executor: &Addr,
+        resp: Response,
+        receiver: Addr,
+        funds: Vec<Coin>,
+        cw20_funds: Vec<Cw20Coin>,
+        payout: bool,
+    ) -> Result<Response, ContractError> {
+        if funds.is_empty() && cw20_funds.is_empty() {
+            return Ok(resp);
+        }
+
+        let resp = match withdrawal_delay(deps.storage, env, &funds, &cw20_funds)? {
+            None => {
+                if payout {
+                    record_payout(deps.storage, &receiver, &funds, &cw20_funds)?;
+                }
+                send_funds(
+                    deps.storage,
+                    env,
+                    executor,
+                    resp,
+                    receiver,
+                    funds,
+                    cw20_funds,
+                )?
+            }
+            Some(delay) => {
+                let withdrawal = PendingWithdrawal {
+                    receiver,
+                    funds,
+                    cw20_funds,
+                    eta: env.block.time.plus_seconds(delay),
+                    campaign_id: None,
+                    payout,
+                };
+                queue_withdrawal(deps.storage, resp, withdrawal)?
+            }
+        };
+
+        Ok(resp)
+    }
+
+    pub fn withdraw_to(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        receiver: String,
+        funds: Vec<Coin>,
+        cw20_funds: Vec<Cw20Coin>,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::Withdrawer)?;
+        let receiver = deps.api.addr_validate(&receiver)?;
+
+        release_unbonded(deps.storage, &env)?;
+        let mut balance = available_balance(deps.as_ref(), &env)?;
+
+        // only withdrawing without any limit sends everything
+        // once either limit is given, the coins and tokens not listed in the limits are kept
+        let limited = !funds.is_empty() || !cw20_funds.is_empty();
+
+        // the balance is already without the reserve, so limits can never reach into it
+        if limited {
+            for coin in &mut balance {
+                let limit = funds
+                    .iter()
+                    .find(|c| c.denom == coin.denom)
+                    .map(|c| c.amount)
+                    .unwrap_or(Uint128::zero());
+
+                coin.amount = std::cmp::min(coin.amount, limit);
+            }
+            balance.retain(|coin| !coin.amount.is_zero());
+        }
+
+        // cw20 limits work the same way, with the token address in place of the denom
+        let mut cw20_balance = available_cw20_balance(deps.as_ref(), &env)?;
+        if limited {
+            for token in &mut cw20_balance {
+                let limit = cw20_funds
+                    .iter()
+                    .find(|c| c.address == token.address)
+                    .map(|c| c.amount)
+                    .unwrap_or(Uint128::zero());
+
+                token.amount = std::cmp::min(token.amount, limit);
+            }
+            cw20_balance.retain(|token| !token.amount.is_zero());
+        }
+        record_withdrawn(deps.storage, &balance, &cw20_balance)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "withdraw")
+            .add_attribute("sender", info.sender.as_str());
+
+        send_or_queue(
+            deps,
+            &env,
+            &info.sender,
+            resp,
+            receiver,
+            balance,
+            cw20_balance,
+            false,
+        )
+    }
+
+    // the first step of the ownership handover
+    // proposing a new owner replaces any previous proposal
+    pub fn propose_owner(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        new_owner: String,
+        expiry: Option<Expiration>,
+    ) -> Result<Response, ContractError> {
+        let owner = ensure_owner(deps.as_ref(), &info.sender)?;
+        let new_owner = deps.api.addr_validate(&new_owner)?;
+
+        // a proposal which could never be accepted makes no sense
+        if expiry.is_some_and(|e| e.is_expired(&env.block)) {
+            return Err(ContractError::TransferExpired {});
+        }
+
+        let resp = Response::new()
+            .add_attribute("action", "propose_owner")
+            .add_attribute("sender", info.sender.as_str());
+
+        // the new owner could revoke the guardians before they cancel anything, so it waits too
+        if let Some(timelock) = TIMELOCK.may_load(deps.storage)? {
+            let change = ConfigChange::Owner { new_owner, expiry };
+            return Ok(queue_change(
+                deps.storage,
+                &env,
+                timelock.delay,
+                resp,
+                change,
+            )?);
+        }
+
+        PENDING_OWNER.save(
+            deps.storage,
+            &PendingOwner {
+                owner: new_owner.clone(),
+                expiry,
+            },
+        )?;
+
+        let mut event = Event::new("ownership_proposed")
+            .add_attribute("owner", owner.as_str())
+            .add_attribute("pending_owner", new_owner.as_str());
+        if let Some(expiry) = expiry {
+            event = event.add_attribute("expiry", expiry.to_string());
+        }
+
+        Ok(resp.add_event(event))
+    }
+
+    // the second step - only the proposed owner can take over the contract
+    pub fn accept_ownership(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let pending = PENDING_OWNER
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoPendingOwner {})?;
+
+        if info.sender != pending.owner {
+            return Err(ContractError::NotPendingOwner {
+                pending_owner: pending.owner.to_string(),
+            });
+        }
+
+        if pending.expiry.is_some_and(|e| e.is_expired(&env.block)) {
+            return Err(ContractError::TransferExpired {});
+        }
+
+        // the ownership could have been renounced in the meantime,
+        // but renouncing removes the pending owner too, so it is always there
+        let previous_owner = OWNER.load(deps.storage)?;
+        OWNER.save(deps.storage, &pending.owner)?;
+        PENDING_OWNER.remove(deps.storage);
+
+        let event = Event::new("ownership_transferred")
+            .add_attribute("previous_owner", previous_owner.as_str())
+            .add_attribute("new_owner", pending.owner.as_str());
+
+        let resp = Response::new()
+            .add_event(event)
+            .add_attribute("action", "accept_ownership")
+            .add_attribute("sender", info.sender.as_str());
+
+        Ok(resp)
+    }
+
+    pub fn cancel_ownership_transfer(
+        deps: DepsMut,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let owner = ensure_owner(deps.as_ref(), &info.sender)?;
+
+        let pending = PENDING_OWNER
+            .may_load(deps.storage)?
+            .ok_or(ContractError::NoPendingOwner {})?;
+        PENDING_OWNER.remove(deps.storage);
+
+        let event = Event::new("ownership_transfer_cancelled")
+            .add_attribute("owner", owner.as_str())
+            .add_attribute("pending_owner", pending.owner.as_str());
+
+        let resp = Response::new()
+            .add_event(event)
+            .add_attribute("action", "cancel_ownership_transfer")
+            .add_attribute("sender", info.sender.as_str());
+
+        Ok(resp)
+    }
+
+    // after renouncing nobody can reset the counter or withdraw funds anymore
+    pub fn renounce_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+        let owner = ensure_owner(deps.as_ref(), &info.sender)?;
+
+        OWNER.remove(deps.storage);
+        PENDING_OWNER.remove(deps.storage);
+
+        let event =
+            Event::new("ownership_renounced").add_attribute("previous_owner", owner.as_str());
+
+        let resp = Response::new()
+            .add_event(event)
+            .add_attribute("action", "renounce_ownership")
+            .add_attribute("sender", info.sender.as_str());
+
+        Ok(resp)
+    }
+
+    pub fn grant_role(
+        deps: DepsMut,
+        info: MessageInfo,
+        role: Role,
+        address: String,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+        let address = deps.api.addr_validate(&address)?;
+
+        ROLES.save(deps.storage, (role.as_str(), &address), &())?;
+
+        let resp = Response::new()
+            .add_attribute("action", "grant_role")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("role", role.as_str())
+            .add_attribute("address", address.as_str());
+
+        Ok(resp)
+    }
+
+    pub fn revoke_role(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        role: Role,
+        address: String,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+        let address = deps.api.addr_validate(&address)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "revoke_role")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("role", role.as_str())
+            .add_attribute("address", address.as_str());
+
+        // the guardians have to stay around for the delay to cancel what is already queued
+        if role == Role::Guardian {
+            if let Some(timelock) = TIMELOCK.may_load(deps.storage)? {
+                let change = ConfigChange::RevokeRole { role, address };
+                return Ok(queue_change(
+                    deps.storage,
+                    &env,
+                    timelock.delay,
+                    resp,
+                    change,
+                )?);
+            }
+        }
+
+        ROLES.remove(deps.storage, (role.as_str(), &address));
+
+        Ok(resp)
+    }
+
+    pub fn add_accepted_denom(
+        deps: DepsMut,
+        info: MessageInfo,
+        minimal_donation: Coin,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        save_minimal_donation(deps.storage, &minimal_donation)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "add_accepted_denom")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("minimal_donation", minimal_donation.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn remove_accepted_denom(
+        deps: DepsMut,
+        info: MessageInfo,
+        denom: String,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        MINIMAL_DONATIONS.remove(deps.storage, &denom);
+
+        let resp = Response::new()
+            .add_attribute("action", "remove_accepted_denom")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("denom", denom);
+
+        Ok(resp)
+    }
+
+    pub fn add_accepted_cw20(
+        deps: DepsMut,
+        info: MessageInfo,
+        address: String,
+        minimal_donation: Uint128,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        let address = deps.api.addr_validate(&address)?;
+        save_accepted_cw20(deps.storage, &address, minimal_donation)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "add_accepted_cw20")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("address", address.as_str())
+            .add_attribute("minimal_donation", minimal_donation.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn remove_accepted_cw20(
+        deps: DepsMut,
+        info: MessageInfo,
+        address: String,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        let address = deps.api.addr_validate(&address)?;
+        ACCEPTED_CW20.remove(deps.storage, &address);
+
+        let resp = Response::new()
+            .add_attribute("action", "remove_accepted_cw20")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("address", address.as_str());
+
+        Ok(resp)
+    }
+
+    pub fn set_counting_policy(
+        deps: DepsMut,
+        info: MessageInfo,
+        policy: CountingPolicy,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        validate_counting_policy(&policy)?;
+        COUNTING_POLICY.save(deps.storage, &policy)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_counting_policy")
+            .add_attribute("sender", info.sender.as_str());
+
+        Ok(resp)
+    }
+
+    pub fn set_refund_mode(
+        deps: DepsMut,
+        info: MessageInfo,
+        mode: RefundMode,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        REFUND_MODE.save(deps.storage, &mode)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_refund_mode")
+            .add_attribute("sender", info.sender.as_str());
+
+        Ok(resp)
+    }
+
+    pub fn set_strict_mode(
+        deps: DepsMut,
+        info: MessageInfo,
+        strict: bool,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        STRICT_MODE.save(deps.storage, &strict)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_strict_mode")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("strict", strict.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn open_campaign(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        goal: Vec<Coin>,
+        deadline: Timestamp,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+
+        // campaigns run one after another, so donations are always escrowed for a single one
+        let last_campaign_id = LAST_CAMPAIGN_ID.may_load(deps.storage)?.unwrap_or_default();
+        if let Some(last) = CAMPAIGNS.may_load(deps.storage, last_campaign_id)? {
+            if last.status(env.block.time) == CampaignStatus::Open {
+                return Err(ContractError::CampaignOpen {
+                    campaign_id: last_campaign_id,
+                });
+            }
+        }
+
+        if goal.is_empty() || goal.iter().any(|coin| coin.amount.is_zero()) {
+            return Err(ContractError::InvalidGoal {});
+        }
+        if deadline <= env.block.time {
+            return Err(ContractError::InvalidDeadline {});
+        }
+
+        let campaign_id = last_campaign_id + 1;
+        let campaign = Campaign {
+            goal,
+            deadline,
+            raised: vec![],
+            claimed: false,
+        };
+        CAMPAIGNS.save(deps.storage, campaign_id, &campaign)?;
+        LAST_CAMPAIGN_ID.save(deps.storage, &campaign_id)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "open_campaign")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("campaign_id", campaign_id.to_string())
+            .add_attribute("goal", coins_to_string(&campaign.goal))
+            .add_attribute("deadline", deadline.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn claim_campaign_funds(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        campaign_id: u64,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+
+        let mut campaign = CAMPAIGNS
+            .may_load(deps.storage, campaign_id)?
+            .ok_or(ContractError::CampaignNotFound { campaign_id })?;
+
+        match campaign.status(env.block.time) {
+            CampaignStatus::Open => return Err(ContractError::CampaignOpen { campaign_id }),
+            CampaignStatus::Failed => return Err(ContractError::GoalNotMet { campaign_id }),
+            CampaignStatus::Claimed => return Err(ContractError::CampaignClaimed { campaign_id }),
+            CampaignStatus::Succeeded => (),
+        }
+
+        // the funds are not held for donors anymore
+        let mut escrow = ESCROW.may_load(deps.storage)?.unwrap_or_default();
+        for coin in &campaign.raised {
+            sub_coin(&mut escrow, coin);
+        }
+        ESCROW.save(deps.storage, &escrow)?;
+
+        campaign.claimed = true;
+        CAMPAIGNS.save(deps.storage, campaign_id, &campaign)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "claim_campaign_funds")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("campaign_id", campaign_id.to_string());
+
+        // the raised funds are subject to the timelock like any other withdrawal
+        // the queued claim remembers its campaign, as its funds are not counted as withdrawn
+        let resp = match withdrawal_delay(deps.storage, &env, &campaign.raised, &[])? {
+            None => send_funds(
+                deps.storage,
+                &env,
+                &info.sender,
+                resp,
+                info.sender.clone(),
+                campaign.raised,
+                vec![],
+            )?,
+            Some(delay) => {
+                let withdrawal = PendingWithdrawal {
+                    receiver: info.sender,
+                    funds: campaign.raised,
+                    cw20_funds: vec![],
+                    eta: env.block.time.plus_seconds(delay),
+                    campaign_id: Some(campaign_id),
+                    payout: false,
+                };
+                queue_withdrawal(deps.storage, resp, withdrawal)?
+            }
+        };
+
+        Ok(resp)
+    }
+
+    pub fn pause(
+        deps: DepsMut,
+        info: MessageInfo,
+        messages: Option<Vec<String>>,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::Pauser)?;
+
+        let mut paused = PAUSED.may_load(deps.storage)?.unwrap_or(Paused {
+            all: false,
+            messages: vec![],
+        });
+        match &messages {
+            None => paused.all = true,
+            Some(messages) => {
+                for message in messages {
+                    if !ExecMsg::PAUSABLE.contains(&message.as_str()) {
+                        return Err(ContractError::UnknownMessage {
+                            message: message.clone(),
+                        });
+                    }
+                    if !paused.messages.contains(message) {
+                        paused.messages.push(message.clone());
+                    }
+                }
+            }
+        }
+        PAUSED.save(deps.storage, &paused)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "pause")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute(
+                "paused",
+                match messages {
+                    None => "all".to_owned(),
+                    Some(messages) => messages.join(","),
+                },
+            );
+
+        Ok(resp)
+    }
+
+    pub fn unpause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::Pauser)?;
+
+        PAUSED.remove(deps.storage);
+
+        let resp = Response::new()
+            .add_attribute("action", "unpause")
+            .add_attribute("sender", info.sender.as_str());
+
+        Ok(resp)
+    }
+
+    fn validate_payees(deps: Deps, payees: &[Payee]) -> Result<Vec<PayeeShare>, ContractError> {
+        let mut shares: Vec<PayeeShare> = vec![];
+        for payee in payees {
+            let address = deps.api.addr_validate(&payee.address)?;
+            if payee.share == 0 || shares.iter().any(|share| share.address == address) {
+                return Err(ContractError::InvalidShares {});
+            }
+            shares.push(PayeeShare {
+                address,
+                share: payee.share,
+            });
+        }
+        if shares
+            .iter()
+            .map(|share| u32::from(share.share))
+            .sum::<u32>()
+            != TOTAL_SHARES
+        {
+            return Err(ContractError::InvalidShares {});
+        }
+
+        Ok(shares)
+    }
+
+    pub fn set_payees(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        payees: Vec<Payee>,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+
+        let shares = validate_payees(deps.as_ref(), &payees)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_payees")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("payees", shares.len().to_string());
+
+        // the payees receive the distributed funds, so with a timelock they can't be changed at once
+        if let Some(timelock) = TIMELOCK.may_load(deps.storage)? {
+            let change = ConfigChange::Payees { payees };
+            return Ok(queue_change(
+                deps.storage,
+                &env,
+                timelock.delay,
+                resp,
+                change,
+            )?);
+        }
+        PAYEES.save(deps.storage, &shares)?;
+
+        Ok(resp)
+    }
+
+    // splits the amount by the payee shares, rounding down
+    // the dust left after rounding goes to the first payee
+    fn split_by_shares(amount: Uint128, payees: &[PayeeShare]) -> Vec<Uint128> {
+        let mut parts: Vec<_> = payees
+            .iter()
+            .map(|payee| amount.multiply_ratio(payee.share, TOTAL_SHARES))
+            .collect();
+        let dust = amount - parts.iter().sum::<Uint128>();
+        if let Some(first) = parts.first_mut() {
+            *first += dust;
+        }
+
+        parts
+    }
+
+    pub fn distribute(
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let payees = PAYEES.may_load(deps.storage)?.unwrap_or_default();
+        if !payees.iter().any(|payee| payee.address == info.sender) {
+            return Err(ContractError::NotPayee {});
+        }
+
+        let balance = available_balance(deps.as_ref(), &env)?;
+        let cw20_balance = available_cw20_balance(deps.as_ref(), &env)?;
+        record_withdrawn(deps.storage, &balance, &cw20_balance)?;
+
+        let mut payouts = vec![vec![]; payees.len()];
+        let mut cw20_payouts = vec![vec![]; payees.len()];
+        for coin in &balance {
+            for (i, amount) in split_by_shares(coin.amount, &payees)
+                .into_iter()
+                .enumerate()
+            {
+                if !amount.is_zero() {
+                    payouts[i].push(Coin::new(amount.u128(), &coin.denom));
+                }
+            }
+        }
+        for token in &cw20_balance {
+            for (i, amount) in split_by_shares(token.amount, &payees)
+                .into_iter()
+                .enumerate()
+            {
+                if !amount.is_zero() {
+                    cw20_payouts[i].push(Cw20Coin {
+                        address: token.address.clone(),
+                        amount,
+                    });
+                }
+            }
+        }
+
+        let mut resp = Response::new()
+            .add_attribute("action", "distribute")
+            .add_attribute("sender", info.sender.as_str());
+
+        for ((payee, coins), tokens) in payees.iter().zip(payouts).zip(cw20_payouts) {
+            if coins.is_empty() && tokens.is_empty() {
+                continue;
+            }
+
+            // every payout is a withdrawal of its own, so the large ones wait for the timelock
+            let receiver = payee.address.clone();
+            resp = send_or_queue(
+                deps.branch(),
+                &env,
+                &info.sender,
+                resp,
+                receiver,
+                coins,
+                tokens,
+                true,
+            )?;
+        }
+
+        Ok(resp)
+    }
+
+    // replaces all the reserves, denoms not listed have no reserve
+    pub fn set_reserves(
+        deps: DepsMut,
+        info: MessageInfo,
+        reserves: Vec<Coin>,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::ConfigAdmin)?;
+
+        let denoms = RESERVES
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for denom in denoms {
+            RESERVES.remove(deps.storage, &denom);
+        }
+        for reserve in &reserves {
+            if !reserve.amount.is_zero() {
+                RESERVES.save(deps.storage, &reserve.denom, &reserve.amount)?;
+            }
+        }
+
+        let mut resp = Response::new()
+            .add_attribute("action", "set_reserves")
+            .add_attribute("sender", info.sender.as_str());
+        if !reserves.is_empty() {
+            resp = resp.add_attribute("reserves", coins_to_string(&reserves));
+        }
+
+        Ok(resp)
+    }
+
+    pub fn set_timelock(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        timelock: Option<Timelock>,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+
+        let mut resp = Response::new()
+            .add_attribute("action", "set_timelock")
+            .add_attribute("sender", info.sender.as_str());
+        if let Some(timelock) = &timelock {
+            resp = resp.add_attribute("delay", timelock.delay.to_string());
+        }
 
-pub mod query {
-    use crate::msg::ValueResp;
-    use cosmwasm_std::{Deps, StdResult};
-    use crate::state::COUNTER;
-    
-    // query::value() function will be called when QueryMsg::Value {} is received
-    // returns an arbitrary object which would be serialized before sending as a response
-    // pub fn value() -> ValueResp {
-    //     ValueResp { value: 0 }
-    // }
-    // loading a value from the state
-    // adding Deps argument to have access to contract storage
-    // returning an error in case loading from the state fails
-    // the returned value has to be wrapped in Ok(...)
-    pub fn value(deps: Deps) -> StdResult<ValueResp> {
-        // utilizing the load function to load from the state
-        // taking the state accessor as an argument
-        let value = COUNTER.load(deps.storage)?;
-        Ok(ValueResp { value })
+        // a compromised owner key could otherwise drop the timelock right before withdrawing,
+        // so only making it stricter takes effect at once
+        if let Some(current) = TIMELOCK.may_load(deps.storage)? {
+            if weakens_timelock(&current, timelock.as_ref()) {
+                let change = ConfigChange::Timelock { timelock };
+                return Ok(queue_change(
+                    deps.storage,
+                    &env,
+                    current.delay,
+                    resp,
+                    change,
+                )?);
+            }
+        }
+        apply_change(deps, ConfigChange::Timelock { timelock })?;
+
+        Ok(resp)
     }
-    
-    // assignment lesson 3: add query to the contract 
-    // taking a single number as its argument and returning the send argument incremented
-    /* 
-    pub fn incremented(value: u64) -> ValueResp {
-        ValueResp { value: value + 1 }
+
+    // the new timelock is weaker if it is shorter, or lets more of any denom through without delay
+    fn weakens_timelock(current: &Timelock, new: Option<&Timelock>) -> bool {
+        let Some(new) = new else {
+            return true;
+        };
+
+        new.delay < current.delay
+            || current.thresholds.iter().any(|threshold| {
+                // the denoms without a threshold are never queued
+                new.thresholds
+                    .iter()
+                    .find(|new| new.denom == threshold.denom)
+                    .is_none_or(|new| new.amount > threshold.amount)
+            })
     }
-    */
-}
 
-/// creating a message handler for the execute entry point
-pub mod exec {
-    // use cosmwasm_std::{DepsMut, MessageInfo, Response, StdResult};
-    use cosmwasm_std::{
-        Coin, BankMsg, DepsMut, Env, MessageInfo, Response, StdResult, Uint128
-    };
- 
-    use crate::{error::ContractError, state::{COUNTER, MINIMAL_DONATION, OWNER}};
+    fn queue_change(
+        storage: &mut dyn Storage,
+        env: &Env,
+        delay: u64,
+        resp: Response,
+        change: ConfigChange,
+    ) -> StdResult<Response> {
+        let change_id = LAST_CHANGE_ID.may_load(storage)?.unwrap_or_default() + 1;
+        let eta = env.block.time.plus_seconds(delay);
+        PENDING_CHANGES.save(storage, change_id, &PendingChange { change, eta })?;
+        LAST_CHANGE_ID.save(storage, &change_id)?;
 
-    // adding the MessageInfo to the update function
-    // -> MessageInfo contains additional metadata about the sent message 
-    // (message sender and the funds sent)
-    // replacing poke with donate functionality
-    // pub fn poke(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
-    pub fn donate(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
-        // similar to instantiate, but instead of just storing value in the COUNTER
-        // the update function is used to update the underlying value
+        let resp = resp
+            .add_attribute("change_id", change_id.to_string())
+            .add_attribute("eta", eta.to_string());
 
-        // the update function takes the (1) borrow to the storage object 
-        // and then (2) the closure, which would be executed on the underlying object
-        
-        // the value returned by the closure should be a Result 
-        // with the type stored as a COUNTER in an Ok variant
-        // the Err variant can be anything implementing From<cosmwasm_std::StdError>
+        Ok(resp)
+    }
 
-        // Rust has to know what type it should use because the error type is never used here
-        // the type hint for the type returned from closure has to be provided
-        /* 
-        COUNTER.update(
-            deps.storage, 
-            |counter| -> StdResult<_> { Ok(counter + 1) }
-        )?;
-        */
-        
-        // splitting updating the counter to keep the new counter value for further usage
+    fn apply_change(deps: DepsMut, change: ConfigChange) -> Result<(), ContractError> {
+        match change {
+            ConfigChange::Timelock {
+                timelock: Some(timelock),
+            } => TIMELOCK.save(deps.storage, &timelock)?,
+            ConfigChange::Timelock { timelock: None } => TIMELOCK.remove(deps.storage),
+            ConfigChange::Payees { payees } => {
+                let shares = validate_payees(deps.as_ref(), &payees)?;
+                PAYEES.save(deps.storage, &shares)?;
+            }
+            // the proposal still has to be accepted by the new owner
+            ConfigChange::Owner { new_owner, expiry } => PENDING_OWNER.save(
+                deps.storage,
+                &PendingOwner {
+                    owner: new_owner,
+                    expiry,
+                },
+            )?,
+            ConfigChange::RevokeRole { role, address } => {
+                ROLES.remove(deps.storage, (role.as_str(), &address))
+            }
+        }
 
-        // let counter = COUNTER.load(deps.storage)? + 1;
-        // COUNTER.save(deps.storage, &counter)?;
-        let mut counter = COUNTER.load(deps.storage)?;
-        let minimal_donation = MINIMAL_DONATION.load(deps.storage)?;
+        Ok(())
+    }
 
-        // we're having a minimal donation you want to count
-        // now we want to iterate through all the funds sent to the contract 
-        // and find out if there is any which is of expected denom, and minimal amount
-        // funds sent with the message can be addressed using the funds field of the info argument
-        if info.funds.iter().any(|coin| {
-            // to filter interesting donations
-            // you first need to load a minimal donation from the state
-            coin.denom == minimal_donation.denom && coin.amount >= minimal_donation.amount
-        }) {
-            // not loading a counter if it should not be incremented to save gas
-            counter += 1;
-            COUNTER.save(deps.storage, &counter)?;
-        }
-        
-        // every execution emits events (logs reporting what was perfromed by an action)
-        // an event contains a type and the set of key-value pairs named attributes
+    pub fn execute_withdrawal(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        withdrawal_id: u64,
+    ) -> Result<Response, ContractError> {
+        let withdrawal = PENDING_WITHDRAWALS
+            .may_load(deps.storage, withdrawal_id)?
+            .ok_or(ContractError::WithdrawalNotFound { withdrawal_id })?;
+        if env.block.time < withdrawal.eta {
+            return Err(ContractError::WithdrawalLocked {
+                eta: withdrawal.eta,
+            });
+        }
 
-        // events are emitted from execution using the Response::add_event function
-        // passing the constructed Event type
+        PENDING_WITHDRAWALS.remove(deps.storage, withdrawal_id);
+        if withdrawal.payout {
+            record_payout(
+                deps.storage,
+                &withdrawal.receiver,
+                &withdrawal.funds,
+                &withdrawal.cw20_funds,
+            )?;
+        }
 
-        // every execution emits at least one default event
-        // to add attributes to the wasm event we can use a Response::add_attribute function
-        // adding three attributes to Response object: action, sender & counter
         let resp = Response::new()
-            .add_attribute("action", "poke")
+            .add_attribute("action", "execute_withdrawal")
             .add_attribute("sender", info.sender.as_str())
-            .add_attribute("counter", counter.to_string());
- 
-        // Ok(Response::new())
-        Ok(resp)
+            .add_attribute("withdrawal_id", withdrawal_id.to_string());
 
+        Ok(send_funds(
+            deps.storage,
+            &env,
+            &info.sender,
+            resp,
+            withdrawal.receiver,
+            withdrawal.funds,
+            withdrawal.cw20_funds,
+        )?)
     }
-    
-    // assignment lesson 6: adding another execution message
-    // which resets an internal counter (setting it to given value)
-    pub fn reset(deps: DepsMut, info: MessageInfo, counter: u64) -> Result<Response, ContractError>  {
-        
-        COUNTER.save(deps.storage, &counter)?;
 
-        let owner = OWNER.load(deps.storage)?;
-        if info.sender != owner {
-            // return Err(StdError::generic_err("Unauthorized"));
-            return Err(ContractError::Unauthorized {
-                owner: owner.to_string(),
-            });
+    pub fn cancel_withdrawal(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        withdrawal_id: u64,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::Guardian)?;
+
+        let withdrawal = PENDING_WITHDRAWALS
+            .may_load(deps.storage, withdrawal_id)?
+            .ok_or(ContractError::WithdrawalNotFound { withdrawal_id })?;
+        if env.block.time >= withdrawal.eta {
+            return Err(ContractError::WithdrawalUnlocked { withdrawal_id });
+        }
+
+        // the cancelled funds go back to the withdrawable balance
+        PENDING_WITHDRAWALS.remove(deps.storage, withdrawal_id);
+        if withdrawal.campaign_id.is_none() {
+            unrecord_withdrawn(deps.storage, &withdrawal.funds, &withdrawal.cw20_funds)?;
         }
 
         let resp = Response::new()
-            .add_attribute("action", "reset")
+            .add_attribute("action", "cancel_withdrawal")
             .add_attribute("sender", info.sender.as_str())
-            .add_attribute("counter", counter.to_string());
+            .add_attribute("withdrawal_id", withdrawal_id.to_string());
 
         Ok(resp)
     }
-    
-    // handler for the execution message variant
-    pub fn withdraw(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
-        let owner = OWNER.load(deps.storage)?;
-        // we need to check if the message sender is the one who created a contract
-        if info.sender != owner {
-            // if not, we immediately fail execution with some generic error
-            // return Err(StdError::generic_err("Unauthorized"));
-            // using our custom error type for our contract instead
-            return Err(ContractError::Unauthorized {
-                owner: owner.to_string(),
-            });
+
+    pub fn execute_change(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        change_id: u64,
+    ) -> Result<Response, ContractError> {
+        let pending = PENDING_CHANGES
+            .may_load(deps.storage, change_id)?
+            .ok_or(ContractError::ChangeNotFound { change_id })?;
+        if env.block.time < pending.eta {
+            return Err(ContractError::ChangeLocked { eta: pending.eta });
         }
-        
-        // then we need to figure out how much funds to send to the contract owner
-        // because we want to send all the funds we query the blockchain for its state 
-        // via a a querier object on the deps argument
-        // to get the contract's address we use the env entry point argument
-        // it contains all relevant meta information like the currently executed contract address
-        let balance = deps.querier.query_all_balances(&env.contract.address)?;
-        // preparing the message for the blockchain: the message we are looking for is a BankMsg
-        // particularly the Send variant of a BankMsg
-        // it takes a funds receiver and amount
-        let bank_msg = BankMsg::Send {
-            to_address: info.sender.to_string(),
-            amount: balance,
-        };
-        
-        // we can add the Send variant of a BankMsg to the Response using the add_message method
+
+        PENDING_CHANGES.remove(deps.storage, change_id);
+        apply_change(deps, pending.change)?;
+
         let resp = Response::new()
-            .add_message(bank_msg)
-            .add_attribute("action", "withdraw")
+            .add_attribute("action", "execute_change")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("change_id", change_id.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn cancel_change(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        change_id: u64,
+    ) -> Result<Response, ContractError> {
+        ensure_role(deps.as_ref(), &info.sender, Role::Guardian)?;
+
+        let pending = PENDING_CHANGES
+            .may_load(deps.storage, change_id)?
+            .ok_or(ContractError::ChangeNotFound { change_id })?;
+        if env.block.time >= pending.eta {
+            return Err(ContractError::ChangeUnlocked { change_id });
+        }
+
+        PENDING_CHANGES.remove(deps.storage, change_id);
+
+        let resp = Response::new()
+            .add_attribute("action", "cancel_change")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("change_id", change_id.to_string());
+
+        Ok(resp)
+    }
+
+    // forgets the completed unbondings, their funds are back in the balance
+    fn release_unbonded(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+        let unbonding = pending_unbonding(storage, env)?;
+        UNBONDING.save(storage, &unbonding)
+    }
+
+    pub fn set_staking(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        staking: Option<StakingConfig>,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+
+        // the contract keeps track of a single delegation, so it has to be gone before switching
+        if let Some(current) = STAKING.may_load(deps.storage)? {
+            let switched = staking
+                .as_ref()
+                .is_none_or(|staking| staking.validator != current.validator);
+            let delegated = deps
+                .querier
+                .query_delegation(&env.contract.address, &current.validator)?
+                .is_some();
+            if switched && delegated {
+                return Err(ContractError::StillDelegated {
+                    validator: current.validator,
+                });
+            }
+        }
+
+        let mut resp = Response::new()
+            .add_attribute("action", "set_staking")
             .add_attribute("sender", info.sender.as_str());
-     
+
+        match staking {
+            Some(staking) => {
+                if deps.querier.query_validator(&staking.validator)?.is_none() {
+                    return Err(ContractError::ValidatorNotFound {
+                        validator: staking.validator,
+                    });
+                }
+                resp = resp.add_attribute("validator", &staking.validator);
+                STAKING.save(deps.storage, &staking)?;
+            }
+            None => STAKING.remove(deps.storage),
+        }
+
         Ok(resp)
     }
-    
-    pub fn withdraw_to(
+
+    pub fn stake(
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        receiver: String,
-        funds: Vec<Coin>,
+        amount: Uint128,
     ) -> Result<Response, ContractError> {
-        let owner = OWNER.load(deps.storage)?;
-        if info.sender != owner {
-            // return Err(StdError::generic_err("Unauthorized"));
-            return Err(ContractError::Unauthorized {
-                owner: owner.to_string(),
-            });
+        ensure_owner(deps.as_ref(), &info.sender)?;
+        let staking = STAKING
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingNotConfigured {})?;
+
+        // only idle funds can be staked - nothing held for anything else, and nothing of the reserve
+        let denom = deps.querier.query_bonded_denom()?;
+        let balance = withdrawable_balance(deps.as_ref(), &env)?
+            .into_iter()
+            .find(|coin| coin.denom == denom)
+            .map_or(Uint128::zero(), |coin| coin.amount);
+        let reserve = RESERVES.may_load(deps.storage, &denom)?.unwrap_or_default();
+        let available = coin(balance.saturating_sub(reserve).u128(), denom);
+        if amount.is_zero() || amount > available.amount {
+            return Err(ContractError::InsufficientFunds { available });
+        }
+
+        let amount = coin(amount.u128(), available.denom);
+        let resp = Response::new()
+            .add_message(StakingMsg::Delegate {
+                validator: staking.validator.clone(),
+                amount: amount.clone(),
+            })
+            .add_attribute("action", "stake")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("validator", staking.validator)
+            .add_attribute("amount", amount.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn unstake(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        amount: Option<Uint128>,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+        let staking = STAKING
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingNotConfigured {})?;
+
+        let bonded = match deps
+            .querier
+            .query_delegation(&env.contract.address, &staking.validator)?
+        {
+            Some(delegation) => delegation.amount,
+            None => coin(0, deps.querier.query_bonded_denom()?),
+        };
+        let amount = amount.unwrap_or(bonded.amount);
+        if amount.is_zero() || amount > bonded.amount {
+            return Err(ContractError::InsufficientStake { bonded });
         }
 
-        let mut balance = deps.querier.query_all_balances(&env.contract.address)?;
+        let amount = coin(amount.u128(), bonded.denom);
+        let completion = env.block.time.plus_seconds(staking.unbonding_period);
+        let mut unbonding = pending_unbonding(deps.storage, &env)?;
+        unbonding.push(Unbonding {
+            amount: amount.clone(),
+            completion,
+        });
+        UNBONDING.save(deps.storage, &unbonding)?;
 
-        if !funds.is_empty() {
-            for coin in &mut balance {
-                let limit = funds
-                    .iter()
-                    .find(|c| c.denom == coin.denom)
-                    .map(|c| c.amount)
-                    .unwrap_or(Uint128::zero());
+        let resp = Response::new()
+            .add_message(StakingMsg::Undelegate {
+                validator: staking.validator,
+                amount: amount.clone(),
+            })
+            .add_attribute("action", "unstake")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("completion", completion.to_string());
 
-                coin.amount = std::cmp::min(coin.amount, limit);
+        Ok(resp)
+    }
+
+    // claimed rewards land in the balance like donations
+    // compounding delegates the rewards in the bonded denom again, once they are withdrawn
+    pub fn claim_rewards(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        compound: bool,
+    ) -> Result<Response, ContractError> {
+        ensure_owner(deps.as_ref(), &info.sender)?;
+        let staking = STAKING
+            .may_load(deps.storage)?
+            .ok_or(ContractError::StakingNotConfigured {})?;
+
+        let delegation = deps
+            .querier
+            .query_delegation(&env.contract.address, &staking.validator)?
+            .filter(|delegation| !delegation.accumulated_rewards.is_empty())
+            .ok_or(ContractError::NoRewards {})?;
+
+        let mut resp = Response::new()
+            .add_message(DistributionMsg::WithdrawDelegatorReward {
+                validator: staking.validator.clone(),
+            })
+            .add_attribute("action", "claim_rewards")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("rewards", coins_to_string(&delegation.accumulated_rewards));
+
+        if compound {
+            let restaked = delegation
+                .accumulated_rewards
+                .into_iter()
+                .find(|reward| reward.denom == delegation.amount.denom);
+            if let Some(restaked) = restaked {
+                resp = resp
+                    .add_message(StakingMsg::Delegate {
+                        validator: staking.validator,
+                        amount: restaked.clone(),
+                    })
+                    .add_attribute("compounded", restaked.to_string());
+            }
+        }
+
+        Ok(resp)
+    }
+
+    pub fn retry_payout(
+        deps: DepsMut,
+        info: MessageInfo,
+        payout_id: u64,
+    ) -> Result<Response, ContractError> {
+        let failed = FAILED_PAYOUTS
+            .may_load(deps.storage, payout_id)?
+            .ok_or(ContractError::PayoutNotFound { payout_id })?;
+
+        // the payout is in flight again, the reply decides if it failed once more
+        FAILED_PAYOUTS.remove(deps.storage, payout_id);
+        SENT_PAYOUTS.save(deps.storage, payout_id, &failed.payout)?;
+
+        let resp = Response::new()
+            .add_submessage(SubMsg::reply_always(payout_msg(&failed.payout)?, payout_id))
+            .add_attribute("action", "retry_payout")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("payout_id", payout_id.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn sponsor_matching(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        ratio: Decimal,
+        cap: Uint128,
+        deadline: Timestamp,
+    ) -> Result<Response, ContractError> {
+        let deposit = one_coin(&info)?;
+        if ratio.is_zero() || cap.is_zero() {
+            return Err(ContractError::InvalidMatchingPool {});
+        }
+        if deadline <= env.block.time {
+            return Err(ContractError::InvalidDeadline {});
+        }
+
+        // a sponsor has a single pool, it can only be replaced once nothing is left of it
+        if let Some(pool) = MATCHING_POOLS.may_load(deps.storage, &info.sender)? {
+            if !pool.remaining.amount.is_zero() {
+                return Err(ContractError::MatchingPoolExists {
+                    sponsor: info.sender.to_string(),
+                });
             }
         }
 
-        let bank_msg = BankMsg::Send {
-            to_address: receiver,
-            amount: balance,
+        let pool = MatchingPool {
+            remaining: deposit,
+            ratio,
+            cap,
+            matched: Uint128::zero(),
+            deadline,
         };
+        MATCHING_POOLS.save(deps.storage, &info.sender, &pool)?;
 
         let resp = Response::new()
-            .add_message(bank_msg)
-            .add_attribute("action", "withdraw")
-            .add_attribute("sender", info.sender.as_str());
+            .add_attribute("action", "sponsor_matching")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("pool", pool.remaining.to_string())
+            .add_attribute("ratio", ratio.to_string())
+            .add_attribute("cap", cap.to_string())
+            .add_attribute("deadline", deadline.to_string());
+
+        Ok(resp)
+    }
+
+    pub fn reclaim_matching_pool(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let mut pool = MATCHING_POOLS
+            .may_load(deps.storage, &info.sender)?
+            .ok_or_else(|| ContractError::MatchingPoolNotFound {
+                sponsor: info.sender.to_string(),
+            })?;
+        if env.block.time < pool.deadline {
+            return Err(ContractError::MatchingPoolActive {
+                deadline: pool.deadline,
+            });
+        }
+        if pool.remaining.amount.is_zero() {
+            return Err(ContractError::NothingToReclaim {});
+        }
+
+        // the pool is kept with nothing left, so its matching history stays queryable
+        let reclaimed = pool.remaining.clone();
+        pool.remaining.amount = Uint128::zero();
+        MATCHING_POOLS.save(deps.storage, &info.sender, &pool)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "reclaim_matching_pool")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("reclaimed", reclaimed.to_string())
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![reclaimed],
+            });
 
         Ok(resp)
     }
 
-}
\ No newline at end of file
+    pub fn claim_refund(
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let contributions: Vec<(u64, Vec<Coin>)> = CONTRIBUTIONS
+            .prefix(&info.sender)
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()?;
+
+        // contributions to open and succeeded campaigns stay where they are
+        // the refunded contributions don't count anymore
+        let mut refund = vec![];
+        let mut escrow = ESCROW.may_load(deps.storage)?.unwrap_or_default();
+        for (campaign_id, contributed) in contributions {
+            let campaign = CAMPAIGNS.load(deps.storage, campaign_id)?;
+            if campaign.status(env.block.time) != CampaignStatus::Failed {
+                continue;
+            }
+
+            for coin in &contributed {
+                add_coin(&mut refund, coin);
+                sub_coin(&mut escrow, coin);
+            }
+            CONTRIBUTIONS.remove(deps.storage, (&info.sender, campaign_id));
+
+            let count = CONTRIBUTION_COUNTS
+                .may_load(deps.storage, (&info.sender, campaign_id))?
+                .unwrap_or_default();
+            CONTRIBUTION_COUNTS.remove(deps.storage, (&info.sender, campaign_id));
+            unrecord_contribution(deps.branch(), &env, &info.sender, &contributed, count)?;
+        }
+
+        if refund.is_empty() {
+            return Err(ContractError::NothingToRefund {});
+        }
+        ESCROW.save(deps.storage, &escrow)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "claim_refund")
+            .add_attribute("sender", info.sender.as_str())
+            .add_attribute("refunded", coins_to_string(&refund))
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: refund,
+            });
+
+        Ok(resp)
+    }
+}
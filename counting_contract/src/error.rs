@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Coin, OverflowError, StdError, Timestamp, Uint128};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 // deriving the thiserror::Error trait generates all the boilerplate
@@ -15,4 +16,105 @@ pub enum ContractError {
     // needs error attribute with some format string how to print the error
     #[error("Unauthorized - only {owner} can call it")]
     Unauthorized { owner: String },
-}
\ No newline at end of file
+    // the owner renounced the ownership, so there is no one to perform owner-only actions
+    #[error("Contract has no owner")]
+    NoOwner {},
+    #[error("There is no pending ownership transfer")]
+    NoPendingOwner {},
+    #[error("Only the pending owner {pending_owner} can accept the ownership")]
+    NotPendingOwner { pending_owner: String },
+    #[error("Ownership transfer expired")]
+    TransferExpired {},
+    #[error("Missing the {role} role")]
+    MissingRole { role: String },
+    #[error("Minimal donation of {denom} has to be greater than zero")]
+    InvalidMinimalDonation { denom: String },
+    #[error("Tier thresholds have to be non-zero and strictly increasing")]
+    InvalidTiers {},
+    // errors explaining why the donation doesn't count
+    // they are only returned in the strict mode, otherwise such donations are just not counted
+    #[error("No funds sent with the donation")]
+    NoFunds {},
+    #[error("Denom {denom} is not accepted")]
+    InvalidDenom { denom: String },
+    #[error("Donation of {sent} is below the minimal donation of {required}")]
+    BelowMinimum { required: Coin, sent: Coin },
+    #[error("This message doesn't accept funds")]
+    UnexpectedFunds {},
+    #[error("Campaign goal has to list non-zero amounts")]
+    InvalidGoal {},
+    #[error("Deadline has to be in the future")]
+    InvalidDeadline {},
+    #[error("Campaign {campaign_id} is still open")]
+    CampaignOpen { campaign_id: u64 },
+    #[error("Campaign {campaign_id} not found")]
+    CampaignNotFound { campaign_id: u64 },
+    #[error("Campaign {campaign_id} didn't meet its goal")]
+    GoalNotMet { campaign_id: u64 },
+    #[error("Campaign {campaign_id} funds were already claimed")]
+    CampaignClaimed { campaign_id: u64 },
+    #[error("Nothing to refund")]
+    NothingToRefund {},
+    #[error("Memo can't be longer than {max} characters")]
+    MemoTooLong { max: usize },
+    #[error("Badge thresholds have to be non-zero and silver has to be below gold")]
+    InvalidBadgeThresholds {},
+    #[error("Donor badges are soulbound and can't be transferred")]
+    Soulbound {},
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+    #[error("Matching ratio and cap have to be greater than zero")]
+    InvalidMatchingPool {},
+    #[error("Sponsor {sponsor} already has a matching pool with funds left")]
+    MatchingPoolExists { sponsor: String },
+    #[error("Sponsor {sponsor} has no matching pool")]
+    MatchingPoolNotFound { sponsor: String },
+    #[error("Matching pool is active until {deadline}")]
+    MatchingPoolActive { deadline: Timestamp },
+    #[error("Nothing to reclaim")]
+    NothingToReclaim {},
+    #[error("Message {message} is paused")]
+    Paused { message: String },
+    #[error("Unknown message {message}")]
+    UnknownMessage { message: String },
+    #[error("Payee shares have to be non-zero, unique and sum to 10000")]
+    InvalidShares {},
+    #[error("Only payees can distribute the funds")]
+    NotPayee {},
+    #[error("Vesting has to end after it starts")]
+    InvalidVesting {},
+    #[error("Withdrawal {withdrawal_id} not found")]
+    WithdrawalNotFound { withdrawal_id: u64 },
+    #[error("Withdrawal is locked until {eta}")]
+    WithdrawalLocked { eta: Timestamp },
+    #[error("Withdrawal {withdrawal_id} can only be cancelled during its delay")]
+    WithdrawalUnlocked { withdrawal_id: u64 },
+    #[error("Change {change_id} not found")]
+    ChangeNotFound { change_id: u64 },
+    #[error("Change is locked until {eta}")]
+    ChangeLocked { eta: Timestamp },
+    #[error("Change {change_id} can only be cancelled during its delay")]
+    ChangeUnlocked { change_id: u64 },
+    #[error("Staking is not configured")]
+    StakingNotConfigured {},
+    #[error("Validator {validator} not found")]
+    ValidatorNotFound { validator: String },
+    #[error("Funds are still delegated to {validator}")]
+    StillDelegated { validator: String },
+    #[error("Only {available} can be staked")]
+    InsufficientFunds { available: Coin },
+    #[error("Only {bonded} is delegated")]
+    InsufficientStake { bonded: Coin },
+    #[error("No staking rewards to claim")]
+    NoRewards {},
+    #[error("Failed payout {payout_id} not found")]
+    PayoutNotFound { payout_id: u64 },
+    #[error("Counter {name} not found")]
+    CounterNotFound { name: String },
+    #[error("Counter {name} already exists")]
+    CounterExists { name: String },
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+    #[error("Counter can't go above {max}")]
+    CounterLimitReached { max: Uint128 },
+}
@@ -1,65 +1,66 @@
-/// coding along with CosmWasm Academy tutorial part 2, Prepare a project
-/// https://academy.cosmwasm.com/learn/smart-contracts/prepare-a-project
-/// 
-/// code examples and comments are taken from the tutorial
-/// check with 
-/// cosmwasm-check ./target/wasm32-unknown-unknown/release/counting_contract.wasm
+//! coding along with CosmWasm Academy tutorial part 2, Prepare a project
+//! https://academy.cosmwasm.com/learn/smart-contracts/prepare-a-project
+//!
+//! code examples and comments are taken from the tutorial
+//! check with
+//! cosmwasm-check ./target/wasm32-unknown-unknown/release/counting_contract.wasm
 
 use cosmwasm_std::{
-    entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, to_json_binary
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    StdResult,
 };
 
 use error::ContractError;
-use msg::InstantiateMsg;
+use msg::{InstantiateMsg, MigrateMsg};
 
 /// contract module for logic implementation
 /// it's private because it contains internal contract logic
 mod contract;
+mod error;
 pub mod msg;
 /// mod multitest will only be in test, not in binary
 #[cfg(test)]
-pub mod multitest; 
+pub mod multitest;
 mod state;
-mod error;
 
-/// the entry point is the first function called by CosmWasm virtual machine 
+/// the entry point is the first function called by CosmWasm virtual machine
 /// when action is performed on a smart contract
 /// unlike native binaries, smart contracts have multiple entry points
-/// 
+///
 /// the instantiate entry point is called when the smart contract is created for the first time
 /// it's like a constructor for a contract
-/// 
+///
 /// the instantiate arguments:
 /// (1) deps: DepsMut -> a utility type for communicating with the outer world
-///     allows querying and updating the contract state, 
-///     querying another contract state, 
+///     allows querying and updating the contract state,
+///     querying another contract state,
 ///     gives access to an Api object for dealing with CW addresses
 /// (2) env: Env -> an object representing the blockchains state when executing the message
-///     the chain height and id, 
-///     current timestamp, 
+///     the chain height and id,
+///     current timestamp,
 ///     and the called contract address
 /// (3) info: MessageInfo -> contains metainformation about the message which triggered an execution
-///     an address that sends the message 
+///     an address that sends the message
 ///     and chain native tokens sent with the message
 /// (4) msg: Empty -> the message triggering execution itself
-///     for now, it is the Empty type that represents {} JSON, 
+///     for now, it is the Empty type that represents {} JSON,
 ///     but the type of this argument can be anything that is deserializable
-/// 
+///
 /// StdResult<Response> is used as a return tyüe for this simple example
 /// it's an alias for Result<Response, StdError>
-/// 
+///
 /// the entry point is decorated by the #[entry_point] attribute
 /// it's wrapping the whole entry point to the form Wasm runtime understands
-/// 
+///
 /// the instantiate entry point is called when the smart contract is created for the first time
 /// it's like a constructor for a contract
 #[entry_point]
 pub fn instantiate(
-	deps: DepsMut,
-	_env:  Env,
-	info: MessageInfo,
-	msg: InstantiateMsg,
-) -> StdResult<Response> {
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
     // initializing the state
     // storing some default value on contract instantiation
     // storing is done by calling the save method on the accessor (the Item)
@@ -69,38 +70,115 @@ pub fn instantiate(
     // save function returns StdError type so the ? operator is needed
     //COUNTER.save(deps.storage, &0)?;
 
-	//Ok(Response::new())
+    //Ok(Response::new())
     // contract::instantiate(deps, msg.counter)
 
-    contract::instantiate(deps, info, msg.counter, msg.minimal_donation)
+    // contract::instantiate(deps, info, msg.counter, msg.minimal_donation)
+    // the message grew too many fields to pass them one by one
+    // the block height is needed for the counter snapshots
+    contract::instantiate(deps, env, info, msg)
 }
 
 /// the execute entry point is for handling messages which are able to modify contract state
 /// they are used to perform some actual actions
 #[entry_point]
 pub fn execute(
-    deps: DepsMut, 
-    env: Env, 
-    info: MessageInfo, 
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
     msg: msg::ExecMsg,
 ) -> Result<Response, ContractError> {
     use contract::exec;
     use msg::ExecMsg::*;
- 
+
+    exec::ensure_not_paused(deps.as_ref(), &msg)?;
+
+    // donations and matching pool deposits are the only messages expecting funds
+    if !matches!(msg, Donate { .. } | SponsorMatching { .. }) {
+        exec::ensure_no_funds(deps.as_ref(), &info)?;
+    }
+
     match msg {
         // Poke {} => exec::poke(deps, info),
         // return type of the execute entry point has been updated to return the c
         // custom error Result<Response, ContractError>
         // functionality here needs to be updated accordingly
         // Donate {} => exec::donate(deps, info)
-        Donate {} => exec::donate(deps, info).map_err(ContractError::Std),
-        Reset { counter } => exec::reset(deps, info, counter),
+        // Donate {} => exec::donate(deps, info).map_err(ContractError::Std),
+        // donate returns the ContractError now, so it can explain why a donation doesn't count
+        Donate { memo, name } => exec::donate(deps, env, info, memo, name),
+        Receive(msg) => exec::receive(deps, env, info, msg),
+        Reset { counter, name } => exec::reset(deps, env, info, counter, name),
+        CreateCounter {
+            name,
+            minimal_donations,
+            counting_policy,
+            owner,
+        } => exec::create_counter(deps, info, name, minimal_donations, counting_policy, owner),
         // Reset { counter } => exec::reset(deps, info, counter).map_err(ContractError::Std),
         Withdraw {} => exec::withdraw(deps, env, info),
         // WithdrawTo { receiver, funds } => {
         //   exec::withdraw_to(deps, env, info, receiver, funds).map_err(ContractError::Std)
         //},
-        WithdrawTo { receiver, funds } => exec::withdraw_to(deps, env, info, receiver, funds),
+        WithdrawTo {
+            receiver,
+            funds,
+            cw20_funds,
+        } => exec::withdraw_to(deps, env, info, receiver, funds, cw20_funds),
+        ProposeOwner { new_owner, expiry } => {
+            exec::propose_owner(deps, env, info, new_owner, expiry)
+        }
+        AcceptOwnership {} => exec::accept_ownership(deps, env, info),
+        CancelOwnershipTransfer {} => exec::cancel_ownership_transfer(deps, info),
+        RenounceOwnership {} => exec::renounce_ownership(deps, info),
+        GrantRole { role, address } => exec::grant_role(deps, info, role, address),
+        RevokeRole { role, address } => exec::revoke_role(deps, env, info, role, address),
+        AddAcceptedDenom { minimal_donation } => {
+            exec::add_accepted_denom(deps, info, minimal_donation)
+        }
+        RemoveAcceptedDenom { denom } => exec::remove_accepted_denom(deps, info, denom),
+        AddAcceptedCw20 {
+            address,
+            minimal_donation,
+        } => exec::add_accepted_cw20(deps, info, address, minimal_donation),
+        RemoveAcceptedCw20 { address } => exec::remove_accepted_cw20(deps, info, address),
+        SetCountingPolicy { policy } => exec::set_counting_policy(deps, info, policy),
+        SetRefundMode { mode } => exec::set_refund_mode(deps, info, mode),
+        SetStrictMode { strict } => exec::set_strict_mode(deps, info, strict),
+        OpenCampaign { goal, deadline } => exec::open_campaign(deps, env, info, goal, deadline),
+        ClaimCampaignFunds { campaign_id } => {
+            exec::claim_campaign_funds(deps, env, info, campaign_id)
+        }
+        ClaimRefund {} => exec::claim_refund(deps, env, info),
+        SponsorMatching {
+            ratio,
+            cap,
+            deadline,
+        } => exec::sponsor_matching(deps, env, info, ratio, cap, deadline),
+        ReclaimMatchingPool {} => exec::reclaim_matching_pool(deps, env, info),
+        Pause { messages } => exec::pause(deps, info, messages),
+        Unpause {} => exec::unpause(deps, info),
+        SetPayees { payees } => exec::set_payees(deps, env, info, payees),
+        Distribute {} => exec::distribute(deps, env, info),
+        SetReserves { reserves } => exec::set_reserves(deps, info, reserves),
+        SetTimelock { timelock } => exec::set_timelock(deps, env, info, timelock),
+        SetCounterLimit { limit } => exec::set_counter_limit(deps, info, limit),
+        ExecuteWithdrawal { withdrawal_id } => {
+            exec::execute_withdrawal(deps, env, info, withdrawal_id)
+        }
+        CancelWithdrawal { withdrawal_id } => {
+            exec::cancel_withdrawal(deps, env, info, withdrawal_id)
+        }
+        ExecuteChange { change_id } => exec::execute_change(deps, env, info, change_id),
+        CancelChange { change_id } => exec::cancel_change(deps, env, info, change_id),
+        SetStaking { staking } => exec::set_staking(deps, env, info, staking),
+        Stake { amount } => exec::stake(deps, env, info, amount),
+        Unstake { amount } => exec::unstake(deps, env, info, amount),
+        ClaimRewards { compound } => exec::claim_rewards(deps, env, info, compound),
+        RetryPayout { payout_id } => exec::retry_payout(deps, info, payout_id),
+        TransferNft { .. } | SendNft { .. } | Approve { .. } | ApproveAll { .. } => {
+            Err(ContractError::Soulbound {})
+        }
     }
 }
 
@@ -109,47 +187,139 @@ pub fn execute(
 /// -> deps argument is of type Deps instead of DepsMut
 ///    because queries can never modify any blockchain state
 /// -> there is no MessageInfo argument
-///    queries can never depend on who sends the message 
+///    queries can never depend on who sends the message
 ///    or on any query circumstances besides the blockchain state itself
 ///    queries also never have funds sent with them
 /// -> the returned type is not a Response, but a Binary type instead
-///     it is because the query returns arbitrary data to the querier 
+///     it is because the query returns arbitrary data to the querier
 ///     instead of processing a full actor flow which is handled with Response type
 /// -> querys should be pure function calls that give back the smart contract state
 #[entry_point]
-pub fn query(
-    deps: Deps, 
-    _env: Env, 
-    msg: msg::QueryMsg
-) -> StdResult<Binary> {
-    use msg::QueryMsg::*;
+pub fn query(deps: Deps, env: Env, msg: msg::QueryMsg) -> StdResult<Binary> {
     use contract::query;
- 
+    use msg::QueryMsg::*;
+
     match msg {
         // Value {} => to_json_binary(&query::value()),
-        Value {} => to_json_binary(&query::value(deps)?),
+        Value { name } => to_json_binary(&query::value(deps, name)?),
+        ValueAt { height } => to_json_binary(&query::value_at(deps, height)?),
+        ValueHistory { start_after, limit } => {
+            to_json_binary(&query::value_history(deps, start_after, limit)?)
+        }
+        Counters { start_after, limit } => {
+            to_json_binary(&query::counters(deps, start_after, limit)?)
+        }
         // Incremented { value } => to_json_binary(&query::incremented(value)),
+        Ownership {} => to_json_binary(&query::ownership(deps)?),
+        Roles { address } => to_json_binary(&query::roles(deps, address)?),
+        RoleMembers {
+            role,
+            start_after,
+            limit,
+        } => to_json_binary(&query::role_members(deps, role, start_after, limit)?),
+        Donor { address } => to_json_binary(&query::donor(deps, address)?),
+        Donors { start_after, limit } => to_json_binary(&query::donors(deps, start_after, limit)?),
+        AcceptedDenoms {} => to_json_binary(&query::accepted_denoms(deps)?),
+        AcceptedCw20s {} => to_json_binary(&query::accepted_cw20s(deps)?),
+        CountingPolicy {} => to_json_binary(&query::counting_policy(deps)?),
+        RefundMode {} => to_json_binary(&query::refund_mode(deps)?),
+        StrictMode {} => to_json_binary(&query::strict_mode(deps)?),
+        Campaign { campaign_id } => to_json_binary(&query::campaign(deps, env, campaign_id)?),
+        Contribution {
+            campaign_id,
+            address,
+        } => to_json_binary(&query::contribution(deps, campaign_id, address)?),
+        Messages {
+            start_after,
+            limit,
+            order,
+        } => to_json_binary(&query::messages(deps, start_after, limit, order)?),
+        Leaderboard { denom, limit } => to_json_binary(&query::leaderboard(deps, denom, limit)?),
+        PauseStatus {} => to_json_binary(&query::pause_status(deps)?),
+        Reserves {} => to_json_binary(&query::reserves(deps)?),
+        Withdrawable {} => to_json_binary(&query::withdrawable(deps, env)?),
+        WithdrawalLog {
+            receiver,
+            from,
+            to,
+            start_after,
+            limit,
+        } => to_json_binary(&query::withdrawal_log(
+            deps,
+            receiver,
+            from,
+            to,
+            start_after,
+            limit,
+        )?),
+        Timelock {} => to_json_binary(&query::timelock(deps)?),
+        CounterLimit {} => to_json_binary(&query::counter_limit(deps)?),
+        PendingWithdrawals { start_after, limit } => {
+            to_json_binary(&query::pending_withdrawals(deps, start_after, limit)?)
+        }
+        PendingChanges { start_after, limit } => {
+            to_json_binary(&query::pending_changes(deps, start_after, limit)?)
+        }
+        FailedPayouts { start_after, limit } => {
+            to_json_binary(&query::failed_payouts(deps, start_after, limit)?)
+        }
+        StakingStatus {} => to_json_binary(&query::staking_status(deps, env)?),
+        Vested {} => to_json_binary(&query::vested(deps, env)?),
+        Payees {} => to_json_binary(&query::payees(deps)?),
+        Payouts { address } => to_json_binary(&query::payouts(deps, address)?),
+        MatchingPool { sponsor } => to_json_binary(&query::matching_pool(deps, sponsor)?),
+        MatchingPools { start_after, limit } => {
+            to_json_binary(&query::matching_pools(deps, start_after, limit)?)
+        }
+        OwnerOf { token_id, .. } => to_json_binary(&query::owner_of(deps, token_id)?),
+        NftInfo { token_id } => to_json_binary(&query::nft_info(deps, token_id)?),
+        Tokens {
+            owner, start_after, ..
+        } => to_json_binary(&query::tokens(deps, owner, start_after)?),
     }
 }
 
-/// testing the contract with a multitest in a contract wrapper 
+/// the reply entry point is called with the result of a submessage sent by the contract
+/// submessages are like normal messages, but the contract is told how they ended
+/// -> the msg argument is the Reply with the submessage id and its result
+/// -> with the reply_always variant of the SubMsg the reply is called even if the message failed
+///    the failure of the submessage is then reverted alone, without aborting the transaction
+///    as long as the reply itself succeeds
+/// the contract sends its payouts this way, so a failed payout can be recorded and retried
+#[entry_point]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    contract::reply(deps, msg)
+}
+
+/// the migrate entry point is called when the admin replaces the code of the contract
+/// -> the new code gets the chance to update the state left behind by the old one
+/// the counters used to be stored as u64 and are now kept as Uint128
+/// so the migration rewrites the old values in place, under the same keys
+/// a big state is migrated in batches, by migrating to the same code again until it is finished
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    contract::migrate(deps, msg.limit)
+}
+
+/// testing the contract with a multitest in a contract wrapper
 /// the contract wrapper would forward all messages to the proper entry point
 /// -> writing a function that creates such a wrapper
 /// the execute entry point is needed it for multitest
 #[cfg(test)]
 mod test {
     // use cosmwasm_std::{Addr, Empty};
-    use cosmwasm_std::{coin, coins, Addr, Empty};
+    use cosmwasm_std::{coins, Addr, Empty, Uint128};
     use cw_multi_test::{App, Contract, ContractWrapper, Executor};
-    
+
     use crate::error::ContractError;
     use crate::msg::{ExecMsg, InstantiateMsg, QueryMsg, ValueResp};
-    use crate::{execute, instantiate, query};
-    
-    // some cosmos blockchains need to have a contract returned here 
+    use crate::{execute, instantiate, query, reply};
+
+    // some cosmos blockchains need to have a contract returned here
     // but for this tutorial the returned Contract will always be empty
     fn counting_contract() -> Box<dyn Contract<Empty>> {
-        let contract = ContractWrapper::new(execute, instantiate, query);
+        // the reply entry point is needed as well, the withdrawals are sent as submessages
+        let contract = ContractWrapper::new(execute, instantiate, query).with_reply(reply);
         // returning an object which can be used with multitest
         Box::new(contract)
     }
@@ -159,10 +329,10 @@ mod test {
         // an App object is the blockchain simulator
         // creating a default App instance
         let mut app = App::default();
-        
+
         // registering the contract object in the blockchain
         // returns the contract ID
-        // for the test there is no code stored anywhere 
+        // for the test there is no code stored anywhere
         // but it performs an equivalent of storing code on the blockchain
         let contract_id = app.store_code(counting_contract());
 
@@ -193,15 +363,16 @@ mod test {
                 Addr::unchecked("sender"),
                 // &Empty {},
                 &InstantiateMsg {
-                    counter: 10,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(10),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
                 None,
             )
             .unwrap();
-        
+
         // if instantiate_contract() is successful we get back the contract address
         // and can use it to query the contract
 
@@ -212,47 +383,52 @@ mod test {
         // to query the contract, we use the query_wasm_smart function
         let resp: ValueResp = app
             .wrap()
-            .query_wasm_smart(contract_addr, &QueryMsg::Value {})
+            .query_wasm_smart(contract_addr, &QueryMsg::Value { name: None })
             .unwrap();
-    
-        // assert_eq!(resp, ValueResp { value: 0 });
-        assert_eq!(resp, ValueResp { value: 10 });
+
+        // assert_eq!(resp, ValueResp { value: Uint128::new(0) });
+        assert_eq!(
+            resp,
+            ValueResp {
+                value: Uint128::new(10)
+            }
+        );
     }
-/* 
-    // poke got replaced with donate so no test for poking
-    #[test]
-    fn poke() {
-        let mut app = App::default();
-    
-        let contract_id = app.store_code(counting_contract());
-    
-        let contract_addr = app
-            .instantiate_contract(
-                contract_id,
+    /*
+        // poke got replaced with donate so no test for poking
+        #[test]
+        fn poke() {
+            let mut app = App::default();
+
+            let contract_id = app.store_code(counting_contract());
+
+            let contract_addr = app
+                .instantiate_contract(
+                    contract_id,
+                    Addr::unchecked("sender"),
+                    &InstantiateMsg { counter: Uint128::new(0) },
+                    &[],
+                    "Counting contract",
+                    None,
+                )
+                .unwrap();
+
+            app.execute_contract(
                 Addr::unchecked("sender"),
-                &InstantiateMsg { counter: 0 },
+                contract_addr.clone(),
+                &ExecMsg::Poke {},
                 &[],
-                "Counting contract",
-                None,
             )
             .unwrap();
-    
-        app.execute_contract(
-            Addr::unchecked("sender"),
-            contract_addr.clone(),
-            &ExecMsg::Poke {},
-            &[],
-        )
-        .unwrap();
-    
-        let resp: ValueResp = app
-            .wrap()
-            .query_wasm_smart(contract_addr, &QueryMsg::Value {})
-            .unwrap();
-    
-        assert_eq!(resp, ValueResp { value: 1 });
-    }
-*/
+
+            let resp: ValueResp = app
+                .wrap()
+                .query_wasm_smart(contract_addr, &QueryMsg::Value { name: None })
+                .unwrap();
+
+            assert_eq!(resp, ValueResp { value: Uint128::new(1) });
+        }
+    */
     #[test]
     fn donate() {
         let mut app = App::default();
@@ -264,8 +440,9 @@ mod test {
                 contract_id,
                 Addr::unchecked("sender"),
                 &InstantiateMsg {
-                    counter: 0,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(0),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
@@ -276,17 +453,25 @@ mod test {
         app.execute_contract(
             Addr::unchecked("sender"),
             contract_addr.clone(),
-            &ExecMsg::Donate {},
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
             &[],
         )
         .unwrap();
 
         let resp: ValueResp = app
             .wrap()
-            .query_wasm_smart(contract_addr, &QueryMsg::Value {})
+            .query_wasm_smart(contract_addr, &QueryMsg::Value { name: None })
             .unwrap();
 
-        assert_eq!(resp, ValueResp { value: 0 });
+        assert_eq!(
+            resp,
+            ValueResp {
+                value: Uint128::new(0)
+            }
+        );
     }
 
     #[test]
@@ -310,8 +495,9 @@ mod test {
                 contract_id,
                 Addr::unchecked("sender"),
                 &InstantiateMsg {
-                    counter: 0,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(0),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
@@ -322,19 +508,27 @@ mod test {
         app.execute_contract(
             Addr::unchecked("sender"),
             contract_addr.clone(),
-            &ExecMsg::Donate {},
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
             &coins(10, "atom"),
         )
         .unwrap();
 
         let resp: ValueResp = app
             .wrap()
-            .query_wasm_smart(contract_addr, &QueryMsg::Value {})
+            .query_wasm_smart(contract_addr, &QueryMsg::Value { name: None })
             .unwrap();
 
-        assert_eq!(resp, ValueResp { value: 1 });
+        assert_eq!(
+            resp,
+            ValueResp {
+                value: Uint128::new(1)
+            }
+        );
     }
-    
+
     #[test]
     fn reset() {
         let mut app = App::default();
@@ -346,8 +540,9 @@ mod test {
                 contract_id,
                 Addr::unchecked("sender"),
                 &InstantiateMsg {
-                    counter: 0,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(0),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
@@ -358,17 +553,25 @@ mod test {
         app.execute_contract(
             Addr::unchecked("sender"),
             contract_addr.clone(),
-            &ExecMsg::Reset { counter: 10 },
+            &ExecMsg::Reset {
+                counter: Uint128::new(10),
+                name: None,
+            },
             &[],
         )
         .unwrap();
 
         let resp: ValueResp = app
             .wrap()
-            .query_wasm_smart(contract_addr, &QueryMsg::Value {})
+            .query_wasm_smart(contract_addr, &QueryMsg::Value { name: None })
             .unwrap();
 
-        assert_eq!(resp, ValueResp { value: 10 });
+        assert_eq!(
+            resp,
+            ValueResp {
+                value: Uint128::new(10)
+            }
+        );
     }
 
     #[test]
@@ -391,8 +594,9 @@ mod test {
                 contract_id,
                 owner.clone(),
                 &InstantiateMsg {
-                    counter: 0,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(0),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
@@ -403,7 +607,10 @@ mod test {
         app.execute_contract(
             sender.clone(),
             contract_addr.clone(),
-            &ExecMsg::Donate {},
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
             &coins(10, "atom"),
         )
         .unwrap();
@@ -426,7 +633,7 @@ mod test {
             vec![]
         );
     }
-    
+
     #[test]
     fn withdraw_to() {
         let owner = Addr::unchecked("owner");
@@ -447,8 +654,9 @@ mod test {
                 contract_id,
                 owner.clone(),
                 &InstantiateMsg {
-                    counter: 0,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(0),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
@@ -459,7 +667,10 @@ mod test {
         app.execute_contract(
             sender.clone(),
             contract_addr.clone(),
-            &ExecMsg::Donate {},
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
             &coins(10, "atom"),
         )
         .unwrap();
@@ -470,6 +681,7 @@ mod test {
             &ExecMsg::WithdrawTo {
                 receiver: receiver.to_string(),
                 funds: coins(5, "atom"),
+                cw20_funds: vec![],
             },
             &[],
         )
@@ -486,7 +698,7 @@ mod test {
             coins(5, "atom")
         );
     }
-  
+
     #[test]
     fn unauthorized_withdraw_to() {
         let owner = Addr::unchecked("owner");
@@ -501,8 +713,9 @@ mod test {
                 contract_id,
                 owner.clone(),
                 &InstantiateMsg {
-                    counter: 0,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(0),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
@@ -517,6 +730,7 @@ mod test {
                 &ExecMsg::WithdrawTo {
                     receiver: owner.to_string(),
                     funds: vec![],
+                    cw20_funds: vec![],
                 },
                 &[],
             )
@@ -544,8 +758,9 @@ mod test {
                 contract_id,
                 owner.clone(),
                 &InstantiateMsg {
-                    counter: 0,
-                    minimal_donation: coin(10, "atom"),
+                    counter: Uint128::new(0),
+                    minimal_donations: coins(10, "atom"),
+                    ..Default::default()
                 },
                 &[],
                 "Counting contract",
@@ -554,7 +769,15 @@ mod test {
             .unwrap();
 
         let err = app
-            .execute_contract(member, contract_addr, &ExecMsg::Reset { counter: 10 }, &[])
+            .execute_contract(
+                member,
+                contract_addr,
+                &ExecMsg::Reset {
+                    counter: Uint128::new(10),
+                    name: None,
+                },
+                &[],
+            )
             .unwrap_err();
 
         assert_eq!(
@@ -564,4 +787,4 @@ mod test {
             err.downcast().unwrap()
         );
     }
-}
\ No newline at end of file
+}
@@ -1,18 +1,144 @@
-use cosmwasm_std::Coin;
 use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Order, Timestamp, Uint128};
 // use schemars::JsonSchema;
 // use serde::{Deserialize, Serialize};
 use cosmwasm_schema::QueryResponses;
+use cw20::{Cw20Coin, Cw20ReceiveMsg};
+use cw_utils::Expiration;
 
 // #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 // #[serde(rename_all = "snake_case")]
 // replacing precious derives with #[cw_serde]
 #[cw_serde]
+#[derive(Default)]
 pub struct InstantiateMsg {
     #[serde(default)]
-    pub counter: u64,
-    // initializing a minimal donation
-    pub minimal_donation: Coin,
+    pub counter: Uint128,
+    // initializing minimal donations - one for every accepted denom
+    pub minimal_donations: Vec<Coin>,
+    // how much the counter is incremented by a donation
+    #[serde(default)]
+    pub counting_policy: CountingPolicy,
+    // what happens to the funds which don't count
+    #[serde(default)]
+    pub refund_mode: RefundMode,
+    // in the strict mode donations which don't count fail instead of being ignored
+    #[serde(default)]
+    pub strict: bool,
+    // cumulative donations needed for badge tiers above bronze
+    #[serde(default)]
+    pub badge_thresholds: BadgeThresholds,
+    // cw20 tokens counted as donations, with their minimal amounts
+    #[serde(default)]
+    pub accepted_cw20s: Vec<Cw20Coin>,
+    // releases the donations for withdrawal gradually, it can't be changed later on
+    #[serde(default)]
+    pub vesting: Option<VestingSchedule>,
+}
+
+/// how the donations are released for withdrawal over time
+/// everything the contract received is vested together, no matter when it was donated
+#[cw_serde]
+pub enum VestingSchedule {
+    // released evenly between the start and the end
+    Linear { start: Timestamp, end: Timestamp },
+    // nothing is released until the cliff, then everything at once
+    Cliff { at: Timestamp },
+}
+
+impl VestingSchedule {
+    // the part of the total released at the given time
+    pub fn vested(&self, total: Uint128, now: Timestamp) -> Uint128 {
+        match self {
+            VestingSchedule::Linear { start, end } => {
+                if now <= *start {
+                    Uint128::zero()
+                } else if now >= *end {
+                    total
+                } else {
+                    let elapsed = now.nanos() - start.nanos();
+                    let duration = end.nanos() - start.nanos();
+                    total.multiply_ratio(elapsed, duration)
+                }
+            }
+            VestingSchedule::Cliff { at } if now >= *at => total,
+            VestingSchedule::Cliff { .. } => Uint128::zero(),
+        }
+    }
+}
+
+/// cumulative donation needed for the badge tiers, in multiples of the minimal donation
+/// every qualifying donation earns at least the bronze badge
+#[cw_serde]
+pub struct BadgeThresholds {
+    pub silver: Uint128,
+    pub gold: Uint128,
+}
+
+impl Default for BadgeThresholds {
+    fn default() -> Self {
+        Self {
+            silver: Uint128::new(10),
+            gold: Uint128::new(100),
+        }
+    }
+}
+
+#[cw_serde]
+#[derive(Copy, PartialOrd)]
+pub enum BadgeTier {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl BadgeTier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BadgeTier::Bronze => "bronze",
+            BadgeTier::Silver => "silver",
+            BadgeTier::Gold => "gold",
+        }
+    }
+}
+
+/// decides which part of the donated funds is sent back to the donor
+#[cw_serde]
+#[derive(Default, Copy)]
+pub enum RefundMode {
+    // all donated funds are kept by the contract
+    #[default]
+    Disabled,
+    // coins which are not accepted or don't meet the minimal donation are sent back
+    NonQualifying,
+    // on top of that, the part of qualifying coins above what the counting policy counted
+    NonQualifyingAndExcess,
+}
+
+/// decides how much a donation increments the counter
+/// only coins meeting the minimal donation of their denom are taken into account
+#[cw_serde]
+#[derive(Default)]
+pub enum CountingPolicy {
+    // incrementing by one, no matter how many coins qualify
+    #[default]
+    OncePerMessage,
+    // incrementing by one for every qualifying coin
+    OncePerCoin,
+    // incrementing by floor(amount / minimal) for every qualifying coin
+    Proportional,
+    // incrementing by the highest tier reached by every qualifying coin
+    // coins not reaching the first tier don't increment the counter
+    Tiered {
+        tiers: Vec<Tier>,
+    },
+}
+
+#[cw_serde]
+pub struct Tier {
+    // in multiples of the minimal donation of the coin denom
+    pub threshold: Uint128,
+    pub increment: u64,
 }
 
 /// creating a query message
@@ -31,8 +157,167 @@ pub enum QueryMsg {
     // it describes what response type is returned for the particular query
     #[returns(ValueResp)]
     // curly braces here are related to how serde is serializing JSON values
-    Value {},
+    // the name of the counter is optional, without it the default counter is queried
+    Value { name: Option<String> },
+    // the value of the default counter once the block at the height was executed
+    #[returns(ValueResp)]
+    ValueAt { height: u64 },
+    // every change of the default counter, oldest first
+    #[returns(ValueHistoryResp)]
+    ValueHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(Option<CounterLimit>)]
+    CounterLimit {},
+    // named counters, ordered by the name - the default counter is not listed
+    #[returns(CountersResp)]
+    Counters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     // Incremented { value: u64 },
+    // current owner together with a proposed owner, if there is any
+    #[returns(OwnershipResp)]
+    Ownership {},
+    // all the roles granted to the address
+    #[returns(RolesResp)]
+    Roles { address: String },
+    // addresses holding the role, paginated by address
+    #[returns(RoleMembersResp)]
+    RoleMembers {
+        role: Role,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // the donation totals of a single donor
+    #[returns(DonorResp)]
+    Donor { address: String },
+    // the donation totals of all donors, paginated by the donor address
+    #[returns(DonorsResp)]
+    Donors {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // denoms counted as donations together with their minimal amounts
+    #[returns(AcceptedDenomsResp)]
+    AcceptedDenoms {},
+    // cw20 tokens counted as donations together with their minimal amounts
+    #[returns(AcceptedCw20sResp)]
+    AcceptedCw20s {},
+    #[returns(CountingPolicy)]
+    CountingPolicy {},
+    #[returns(RefundMode)]
+    RefundMode {},
+    #[returns(StrictModeResp)]
+    StrictMode {},
+    // progress of the campaign, the latest one if no id is given
+    #[returns(CampaignResp)]
+    Campaign { campaign_id: Option<u64> },
+    // funds escrowed by the donor for the campaign
+    #[returns(ContributionResp)]
+    Contribution { campaign_id: u64, address: String },
+    // donation memos, oldest first unless the order says otherwise
+    #[returns(MessagesResp)]
+    Messages {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        order: Option<SortOrder>,
+    },
+    // top donors by their total qualifying donation of the denom
+    #[returns(LeaderboardResp)]
+    Leaderboard { denom: String, limit: Option<u32> },
+    #[returns(PauseStatusResp)]
+    PauseStatus {},
+    #[returns(ReservesResp)]
+    Reserves {},
+    // what the owner can withdraw right now
+    #[returns(WithdrawableResp)]
+    Withdrawable {},
+    // withdrawals sent so far, oldest first
+    // optionally only the ones to the receiver, and sent from (inclusive) or to (exclusive) the time
+    #[returns(WithdrawalLogResp)]
+    WithdrawalLog {
+        receiver: Option<String>,
+        from: Option<Timestamp>,
+        to: Option<Timestamp>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(Option<Timelock>)]
+    Timelock {},
+    // queued withdrawals, oldest first
+    #[returns(PendingWithdrawalsResp)]
+    PendingWithdrawals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // queued changes of the timelock and the payees, oldest first
+    #[returns(PendingChangesResp)]
+    PendingChanges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // payouts which failed and can be retried, oldest first
+    #[returns(FailedPayoutsResp)]
+    FailedPayouts {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // delegated funds with their unbonding and pending rewards
+    #[returns(StakingStatusResp)]
+    StakingStatus {},
+    // how much of the funds of every denom was released by the vesting schedule
+    #[returns(VestedResp)]
+    Vested {},
+    // the payees with their shares and everything paid to them so far
+    #[returns(PayeesResp)]
+    Payees {},
+    // everything paid to the address, even if it's no longer a payee
+    #[returns(PayoutsResp)]
+    Payouts { address: String },
+    // the matching pool of the sponsor
+    #[returns(MatchingPoolResp)]
+    MatchingPool { sponsor: String },
+    // matching pools of all sponsors, paginated by the sponsor address
+    #[returns(MatchingPoolsResp)]
+    MatchingPools {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // cw721 compatible queries for donor badges
+    // every donor has at most one badge, with the donor address as the token id
+    #[returns(OwnerOfResp)]
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    #[returns(NftInfoResp)]
+    NftInfo { token_id: String },
+    #[returns(TokensResp)]
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+/// cosmwasm_std::Order can't be sent in messages, so queries use their own ordering
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl From<SortOrder> for Order {
+    fn from(order: SortOrder) -> Self {
+        match order {
+            SortOrder::Ascending => Order::Ascending,
+            SortOrder::Descending => Order::Descending,
+        }
+    }
 }
 
 // creating a new message for the execute entry point
@@ -45,16 +330,404 @@ pub enum QueryMsg {
 pub enum ExecMsg {
     // removing poke and adding donate Msg
     // Poke {},
-    Donate {},
+    // the optional memo is posted on the message board along with a qualifying donation
+    // the donation is counted by the named counter, or by the default one if there is no name
+    Donate {
+        memo: Option<String>,
+        name: Option<String>,
+    },
+    // cw20 tokens are donated by sending them to the contract with a ReceiveMsg
+    Receive(Cw20ReceiveMsg),
+    // named counters can only be reset by their owners
     Reset {
-        counter: u64,
+        counter: Uint128,
+        name: Option<String>,
+    },
+    // the maximum of every counter, no limit lets the counters grow until they overflow
+    SetCounterLimit {
+        limit: Option<CounterLimit>,
+    },
+    // a named counter with its own minimal donations and counting policy
+    // the sender owns the counter, unless another owner is given
+    CreateCounter {
+        name: String,
+        minimal_donations: Vec<Coin>,
+        counting_policy: CountingPolicy,
+        owner: Option<String>,
     },
     // execution message variant to send funds
     Withdraw {},
+    // both limits are optional - leaving both lists empty withdraws everything
+    // otherwise only the listed coins and tokens are withdrawn
     WithdrawTo {
         receiver: String,
         funds: Vec<Coin>,
+        #[serde(default)]
+        cw20_funds: Vec<Cw20Coin>,
+    },
+    // ownership is handed over in two steps:
+    // the owner proposes a new owner, who then has to accept it before the expiry
+    // with a timelock the proposal is only made after the delay
+    ProposeOwner {
+        new_owner: String,
+        expiry: Option<Expiration>,
+    },
+    AcceptOwnership {},
+    CancelOwnershipTransfer {},
+    // giving up the ownership for good - no owner-only action can be performed afterwards
+    RenounceOwnership {},
+    // roles allow the owner to delegate privileged actions to other addresses
+    GrantRole {
+        role: Role,
+        address: String,
+    },
+    // with a timelock revoking a guardian waits for the delay
+    RevokeRole {
+        role: Role,
+        address: String,
+    },
+    // adds a new denom to count, or changes the minimal amount of an already accepted one
+    AddAcceptedDenom {
+        minimal_donation: Coin,
+    },
+    RemoveAcceptedDenom {
+        denom: String,
+    },
+    // the same for cw20 tokens, identified by the token contract address
+    AddAcceptedCw20 {
+        address: String,
+        minimal_donation: Uint128,
+    },
+    RemoveAcceptedCw20 {
+        address: String,
+    },
+    SetCountingPolicy {
+        policy: CountingPolicy,
+    },
+    SetRefundMode {
+        mode: RefundMode,
+    },
+    SetStrictMode {
+        strict: bool,
+    },
+    // while the campaign is open, donations of the goal denoms are escrowed for it
+    OpenCampaign {
+        goal: Vec<Coin>,
+        deadline: Timestamp,
+    },
+    // the owner takes the campaign funds after the deadline, if the goal was met
+    ClaimCampaignFunds {
+        campaign_id: u64,
     },
+    // donors take back their contributions to all campaigns which failed
+    ClaimRefund {},
+    // the funds sent with the message become a matching pool of the sender
+    // until the deadline, every qualifying donation of the pool denom is matched by the ratio
+    SponsorMatching {
+        ratio: Decimal,
+        cap: Uint128,
+        deadline: Timestamp,
+    },
+    // the sponsor takes back what is left of the pool after the deadline
+    ReclaimMatchingPool {},
+    // stops all the messages, or only the listed ones - named the same as in JSON, like "withdraw_to"
+    // pausing more messages adds them to the ones already paused
+    Pause {
+        messages: Option<Vec<String>>,
+    },
+    // resumes everything that was paused
+    Unpause {},
+    // replaces the payees sharing the distributed funds, their shares have to sum to 10000
+    // with a timelock set, the new payees are queued for its delay like a withdrawal
+    SetPayees {
+        payees: Vec<Payee>,
+    },
+    // splits the withdrawable balance between the payees, by their shares of every denom
+    Distribute {},
+    // amounts of every denom left in the contract by withdrawals, replacing the previous ones
+    // cw20 tokens use the token address as the denom
+    SetReserves {
+        reserves: Vec<Coin>,
+    },
+    // withdrawals above the thresholds are queued for the delay instead of being sent right away
+    // no timelock sends all withdrawals immediately
+    // removing or weakening the timelock is queued for the delay of the current one
+    SetTimelock {
+        timelock: Option<Timelock>,
+    },
+    // anyone can send a queued withdrawal once its delay passed
+    ExecuteWithdrawal {
+        withdrawal_id: u64,
+    },
+    // the owner or a guardian can cancel a queued withdrawal during its delay
+    CancelWithdrawal {
+        withdrawal_id: u64,
+    },
+    // queued changes of the timelock or the payees work the same way as the queued withdrawals
+    ExecuteChange {
+        change_id: u64,
+    },
+    CancelChange {
+        change_id: u64,
+    },
+    // the validator idle funds are delegated to, no staking disables it
+    // the validator can't be changed while anything is still delegated to the current one
+    SetStaking {
+        staking: Option<StakingConfig>,
+    },
+    // delegates the amount of the staking denom out of the withdrawable balance
+    Stake {
+        amount: Uint128,
+    },
+    // starts unbonding the amount, or everything delegated if there is no amount
+    // unbonded funds come back to the balance once the unbonding period passes
+    Unstake {
+        amount: Option<Uint128>,
+    },
+    // withdraws the staking rewards, compounding delegates them back right away
+    ClaimRewards {
+        compound: bool,
+    },
+    // anyone can resend a failed payout, always to its original receiver
+    RetryPayout {
+        payout_id: u64,
+    },
+    // badges are soulbound - cw721 transfer messages are understood, but always rejected
+    TransferNft {
+        recipient: String,
+        token_id: String,
+    },
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+}
+
+impl ExecMsg {
+    // names of all the messages which can be paused
+    // pausing and unpausing itself is never paused, so the contract can always be resumed
+    pub const PAUSABLE: [&'static str; 42] = [
+        "donate",
+        "receive",
+        "reset",
+        "create_counter",
+        "set_counter_limit",
+        "withdraw",
+        "withdraw_to",
+        "propose_owner",
+        "accept_ownership",
+        "cancel_ownership_transfer",
+        "renounce_ownership",
+        "grant_role",
+        "revoke_role",
+        "add_accepted_denom",
+        "remove_accepted_denom",
+        "add_accepted_cw20",
+        "remove_accepted_cw20",
+        "set_counting_policy",
+        "set_refund_mode",
+        "set_strict_mode",
+        "open_campaign",
+        "claim_campaign_funds",
+        "claim_refund",
+        "sponsor_matching",
+        "reclaim_matching_pool",
+        "set_payees",
+        "distribute",
+        "set_reserves",
+        "set_timelock",
+        "execute_withdrawal",
+        "cancel_withdrawal",
+        "execute_change",
+        "cancel_change",
+        "set_staking",
+        "stake",
+        "unstake",
+        "claim_rewards",
+        "retry_payout",
+        "transfer_nft",
+        "send_nft",
+        "approve",
+        "approve_all",
+    ];
+
+    // the message name as it is sent in JSON
+    pub fn name(&self) -> &'static str {
+        use ExecMsg::*;
+
+        match self {
+            Donate { .. } => "donate",
+            Receive(_) => "receive",
+            Reset { .. } => "reset",
+            CreateCounter { .. } => "create_counter",
+            SetCounterLimit { .. } => "set_counter_limit",
+            Withdraw {} => "withdraw",
+            WithdrawTo { .. } => "withdraw_to",
+            ProposeOwner { .. } => "propose_owner",
+            AcceptOwnership {} => "accept_ownership",
+            CancelOwnershipTransfer {} => "cancel_ownership_transfer",
+            RenounceOwnership {} => "renounce_ownership",
+            GrantRole { .. } => "grant_role",
+            RevokeRole { .. } => "revoke_role",
+            AddAcceptedDenom { .. } => "add_accepted_denom",
+            RemoveAcceptedDenom { .. } => "remove_accepted_denom",
+            AddAcceptedCw20 { .. } => "add_accepted_cw20",
+            RemoveAcceptedCw20 { .. } => "remove_accepted_cw20",
+            SetCountingPolicy { .. } => "set_counting_policy",
+            SetRefundMode { .. } => "set_refund_mode",
+            SetStrictMode { .. } => "set_strict_mode",
+            OpenCampaign { .. } => "open_campaign",
+            ClaimCampaignFunds { .. } => "claim_campaign_funds",
+            ClaimRefund {} => "claim_refund",
+            SponsorMatching { .. } => "sponsor_matching",
+            ReclaimMatchingPool {} => "reclaim_matching_pool",
+            Pause { .. } => "pause",
+            Unpause {} => "unpause",
+            SetPayees { .. } => "set_payees",
+            Distribute {} => "distribute",
+            SetReserves { .. } => "set_reserves",
+            SetTimelock { .. } => "set_timelock",
+            ExecuteWithdrawal { .. } => "execute_withdrawal",
+            CancelWithdrawal { .. } => "cancel_withdrawal",
+            ExecuteChange { .. } => "execute_change",
+            CancelChange { .. } => "cancel_change",
+            SetStaking { .. } => "set_staking",
+            Stake { .. } => "stake",
+            Unstake { .. } => "unstake",
+            ClaimRewards { .. } => "claim_rewards",
+            RetryPayout { .. } => "retry_payout",
+            TransferNft { .. } => "transfer_nft",
+            SendNft { .. } => "send_nft",
+            Approve { .. } => "approve",
+            ApproveAll { .. } => "approve_all",
+        }
+    }
+}
+
+#[cw_serde]
+pub struct CounterLimit {
+    pub max: Uint128,
+    pub on_reached: LimitMode,
+}
+
+/// what happens to a counter which would go above its maximum
+#[cw_serde]
+pub enum LimitMode {
+    // the counter stops at the maximum, the message still succeeds
+    Saturate,
+    // the whole message fails
+    Reject,
+}
+
+#[cw_serde]
+pub struct Timelock {
+    // the largest amount of every denom withdrawn without the delay
+    // cw20 tokens use the token address as the denom, denoms not listed are never queued
+    pub thresholds: Vec<Coin>,
+    // in seconds
+    pub delay: u64,
+}
+
+/// a change which could get the funds around the timelock, so it waits for its delay
+#[cw_serde]
+pub enum ConfigChange {
+    Timelock {
+        timelock: Option<Timelock>,
+    },
+    Payees {
+        payees: Vec<Payee>,
+    },
+    // the new owner could revoke the guardians, so the proposal itself waits
+    Owner {
+        new_owner: Addr,
+        expiry: Option<Expiration>,
+    },
+    // only the guardian role, a guardian is the one cancelling the queued withdrawals
+    RevokeRole {
+        role: Role,
+        address: Addr,
+    },
+}
+
+#[cw_serde]
+pub struct StakingConfig {
+    pub validator: String,
+    // in seconds, the chain's unbonding time, used to tell when unbonded funds are back
+    pub unbonding_period: u64,
+}
+
+/// funds being unbonded from the validator
+#[cw_serde]
+pub struct Unbonding {
+    pub amount: Coin,
+    pub completion: Timestamp,
+}
+
+/// a beneficiary of distributed funds
+#[cw_serde]
+pub struct Payee {
+    pub address: String,
+    // in basis points, 10000 is the whole balance
+    pub share: u16,
+}
+
+/// the message sent along with cw20 tokens in the Cw20ExecuteMsg::Send
+#[cw_serde]
+pub enum ReceiveMsg {
+    // the tokens are counted by the named counter, or by the default one
+    Donate {
+        memo: Option<String>,
+        #[serde(default)]
+        name: Option<String>,
+    },
+}
+
+/// permissions which can be granted by the owner
+/// the owner is allowed to perform every action without holding any role
+#[cw_serde]
+#[derive(Copy)]
+pub enum Role {
+    // can reset the counter
+    Resetter,
+    // can withdraw funds from the contract
+    Withdrawer,
+    // can change the contract configuration
+    ConfigAdmin,
+    // can pause the contract
+    Pauser,
+    // can cancel queued withdrawals
+    Guardian,
+}
+
+impl Role {
+    pub const ALL: [Role; 5] = [
+        Role::Resetter,
+        Role::Withdrawer,
+        Role::ConfigAdmin,
+        Role::Pauser,
+        Role::Guardian,
+    ];
+
+    // the role name used as a part of the storage key
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Resetter => "resetter",
+            Role::Withdrawer => "withdrawer",
+            Role::ConfigAdmin => "config_admin",
+            Role::Pauser => "pauser",
+            Role::Guardian => "guardian",
+        }
+    }
 }
 
 /// second message I created is a response to the Value query
@@ -65,6 +738,318 @@ pub enum ExecMsg {
 // replacing precious derives with #[cw_serde]
 #[cw_serde]
 pub struct ValueResp {
-    pub value: u64,
+    pub value: Uint128,
+}
+
+/// the message migrating the contract to this version
+/// the limit is how many stored rows a single migration rewrites, 100 if not given
+/// the migration is repeated until its "finished" attribute is true
+#[cw_serde]
+pub struct MigrateMsg {
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// what changed the counter
+#[cw_serde]
+pub enum CounterChangeCause {
+    Instantiate,
+    Donate,
+    Reset,
+    // a contribution to a failed campaign was refunded, so it doesn't count anymore
+    Refund,
 }
 
+#[cw_serde]
+pub struct ValueChangeResp {
+    pub change_id: u64,
+    pub value: Uint128,
+    pub height: u64,
+    pub time: Timestamp,
+    pub cause: CounterChangeCause,
+}
+
+#[cw_serde]
+pub struct ValueHistoryResp {
+    pub changes: Vec<ValueChangeResp>,
+}
+
+#[cw_serde]
+pub struct CounterResp {
+    pub name: String,
+    pub value: Uint128,
+    pub owner: Addr,
+    pub minimal_donations: Vec<Coin>,
+    pub counting_policy: CountingPolicy,
+}
+
+#[cw_serde]
+pub struct CountersResp {
+    pub counters: Vec<CounterResp>,
+}
+
+#[cw_serde]
+pub struct OwnershipResp {
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
+    pub pending_expiry: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct RolesResp {
+    pub roles: Vec<Role>,
+}
+
+#[cw_serde]
+pub struct RoleMembersResp {
+    pub members: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct DonorResp {
+    pub address: Addr,
+    // donated coins which met the minimal donation
+    pub qualifying: Vec<Coin>,
+    // donated coins which didn't count
+    pub non_qualifying: Vec<Coin>,
+    // coins added to the qualifying donations from matching pools
+    pub matched: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct DonorsResp {
+    pub donors: Vec<DonorResp>,
+}
+
+#[cw_serde]
+pub struct AcceptedDenomsResp {
+    pub denoms: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct AcceptedCw20sResp {
+    pub tokens: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct StrictModeResp {
+    pub strict: bool,
+}
+
+#[cw_serde]
+pub enum CampaignStatus {
+    Open,
+    // the deadline passed and the goal was met, the funds are waiting for the owner
+    Succeeded,
+    Claimed,
+    // the deadline passed without meeting the goal, donors can claim refunds
+    Failed,
+}
+
+#[cw_serde]
+pub struct PauseStatusResp {
+    // everything is paused
+    pub all: bool,
+    // messages paused on their own
+    pub messages: Vec<String>,
+}
+
+#[cw_serde]
+pub struct ReservesResp {
+    pub reserves: Vec<Coin>,
+}
+
+/// the balance without escrowed, pooled, queued, unvested and reserved funds
+#[cw_serde]
+pub struct WithdrawableResp {
+    pub funds: Vec<Coin>,
+    pub cw20_funds: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct WithdrawalLogEntryResp {
+    pub log_id: u64,
+    pub executor: Addr,
+    pub receiver: Addr,
+    pub funds: Vec<Coin>,
+    pub cw20_funds: Vec<Cw20Coin>,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+#[cw_serde]
+pub struct WithdrawalLogResp {
+    pub entries: Vec<WithdrawalLogEntryResp>,
+}
+
+#[cw_serde]
+pub struct PendingWithdrawalResp {
+    pub withdrawal_id: u64,
+    pub receiver: Addr,
+    pub funds: Vec<Coin>,
+    pub cw20_funds: Vec<Cw20Coin>,
+    // the withdrawal can be executed from this time on
+    pub eta: Timestamp,
+}
+
+#[cw_serde]
+pub struct PendingWithdrawalsResp {
+    pub withdrawals: Vec<PendingWithdrawalResp>,
+}
+
+#[cw_serde]
+pub struct PendingChangeResp {
+    pub change_id: u64,
+    pub change: ConfigChange,
+    // the change can be executed from this time on
+    pub eta: Timestamp,
+}
+
+#[cw_serde]
+pub struct PendingChangesResp {
+    pub changes: Vec<PendingChangeResp>,
+}
+
+#[cw_serde]
+pub struct FailedPayoutResp {
+    pub payout_id: u64,
+    // the entry of the withdrawal log the payout was sent for
+    pub log_id: u64,
+    pub receiver: Addr,
+    pub funds: Vec<Coin>,
+    pub cw20_funds: Vec<Cw20Coin>,
+    pub error: String,
+}
+
+#[cw_serde]
+pub struct FailedPayoutsResp {
+    pub payouts: Vec<FailedPayoutResp>,
+}
+
+#[cw_serde]
+pub struct StakingStatusResp {
+    pub staking: Option<StakingConfig>,
+    pub bonded: Vec<Coin>,
+    pub unbonding: Vec<Unbonding>,
+    pub rewards: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct VestedResp {
+    pub schedule: Option<VestingSchedule>,
+    pub denoms: Vec<VestedDenom>,
+}
+
+/// cw20 tokens are listed with the token address as the denom
+#[cw_serde]
+pub struct VestedDenom {
+    pub denom: String,
+    // released so far, including what was already withdrawn
+    pub vested: Uint128,
+    pub withdrawn: Uint128,
+    // not released yet
+    pub locked: Uint128,
+}
+
+#[cw_serde]
+pub struct PayeeResp {
+    pub address: Addr,
+    pub share: u16,
+    pub paid: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct PayeesResp {
+    pub payees: Vec<PayeeResp>,
+}
+
+#[cw_serde]
+pub struct PayoutsResp {
+    // cw20 payouts are listed with the token address as the denom
+    pub paid: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct MatchingPoolResp {
+    pub sponsor: Addr,
+    pub remaining: Coin,
+    pub ratio: Decimal,
+    pub cap: Uint128,
+    pub matched: Uint128,
+    pub deadline: Timestamp,
+}
+
+#[cw_serde]
+pub struct MatchingPoolsResp {
+    pub pools: Vec<MatchingPoolResp>,
+}
+
+#[cw_serde]
+pub struct CampaignResp {
+    pub campaign_id: u64,
+    pub goal: Vec<Coin>,
+    pub raised: Vec<Coin>,
+    pub deadline: Timestamp,
+    pub status: CampaignStatus,
+}
+
+#[cw_serde]
+pub struct ContributionResp {
+    pub contributed: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct MessageResp {
+    pub id: u64,
+    pub sender: Addr,
+    pub memo: String,
+    pub time: Timestamp,
+}
+
+#[cw_serde]
+pub struct MessagesResp {
+    pub messages: Vec<MessageResp>,
+}
+
+#[cw_serde]
+pub struct LeaderboardEntry {
+    pub address: Addr,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct LeaderboardResp {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+// responses shaped like the cw721 ones, so NFT tooling can read the badges
+#[cw_serde]
+pub struct Approval {
+    pub spender: String,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct OwnerOfResp {
+    pub owner: String,
+    // badges can't be transferred, so there are never any approvals
+    pub approvals: Vec<Approval>,
+}
+
+#[cw_serde]
+pub struct BadgeExtension {
+    pub tier: BadgeTier,
+    // cumulative qualifying donation, in multiples of the minimal donation
+    pub donated_units: Uint128,
+}
+
+#[cw_serde]
+pub struct NftInfoResp {
+    pub token_uri: Option<String>,
+    pub extension: BadgeExtension,
+}
+
+#[cw_serde]
+pub struct TokensResp {
+    pub tokens: Vec<String>,
+}
@@ -0,0 +1,5073 @@
+// multitest tests for the features built on top of the tutorial contract
+// the basic tests from the tutorial live in the test module of lib.rs
+use anyhow::Result as AnyResult;
+use cosmwasm_schema::schema_for;
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{
+    attr, coin, coins, from_json, to_json_binary, Addr, Coin, Decimal, DepsMut, Empty, Env,
+    MessageInfo, Response, StdError, StdResult, Timestamp, Uint128, Validator,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor, StakingInfo};
+use cw_storage_plus::Item;
+use cw_utils::Expiration;
+use schemars::schema::Schema;
+
+use crate::error::ContractError;
+use crate::msg::{
+    AcceptedCw20sResp, AcceptedDenomsResp, BadgeExtension, BadgeThresholds, BadgeTier,
+    CampaignResp, CampaignStatus, ConfigChange, ContributionResp, CounterChangeCause, CounterLimit,
+    CounterResp, CountersResp, CountingPolicy, DonorResp, DonorsResp, ExecMsg, FailedPayoutResp,
+    FailedPayoutsResp, InstantiateMsg, LeaderboardEntry, LeaderboardResp, LimitMode,
+    MatchingPoolResp, MatchingPoolsResp, MessagesResp, MigrateMsg, NftInfoResp, OwnerOfResp,
+    OwnershipResp, PauseStatusResp, Payee, PayeeResp, PayeesResp, PayoutsResp, PendingChangeResp,
+    PendingChangesResp, PendingWithdrawalResp, PendingWithdrawalsResp, QueryMsg, ReceiveMsg,
+    RefundMode, ReservesResp, Role, RoleMembersResp, RolesResp, SortOrder, StakingConfig,
+    StakingStatusResp, Tier, Timelock, TokensResp, Unbonding, ValueChangeResp, ValueHistoryResp,
+    ValueResp, VestedDenom, VestedResp, VestingSchedule, WithdrawableResp, WithdrawalLogEntryResp,
+    WithdrawalLogResp,
+};
+use crate::state::legacy::{self, ChangeSet, Stored};
+use crate::state::{COUNTER, COUNTERS, COUNTER_HISTORY, LAST_COUNTER_CHANGE_ID};
+use crate::{execute, instantiate, migrate, query, reply};
+
+fn counting_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(execute, instantiate, query).with_reply(reply);
+    Box::new(contract)
+}
+
+// stores and instantiates the contract with a counter of 0 and 10atom minimal donation
+fn instantiate_counting(app: &mut App, owner: &Addr) -> Addr {
+    instantiate_with(
+        app,
+        owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            ..Default::default()
+        },
+    )
+}
+
+fn instantiate_with(app: &mut App, owner: &Addr, msg: InstantiateMsg) -> Addr {
+    let contract_id = app.store_code(counting_contract());
+
+    app.instantiate_contract(
+        contract_id,
+        owner.clone(),
+        &msg,
+        &[],
+        "Counting contract",
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn ownership_transfer() {
+    let owner = Addr::unchecked("owner");
+    let new_owner = Addr::unchecked("new_owner");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ProposeOwner {
+            new_owner: new_owner.to_string(),
+            expiry: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: OwnershipResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(
+        resp,
+        OwnershipResp {
+            owner: Some(owner.clone()),
+            pending_owner: Some(new_owner.clone()),
+            pending_expiry: None,
+        }
+    );
+
+    let resp = app
+        .execute_contract(
+            new_owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap();
+    let event = resp
+        .events
+        .iter()
+        .find(|ev| ev.ty == "wasm-ownership_transferred")
+        .unwrap();
+    assert!(event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "new_owner" && attr.value == new_owner.as_str()));
+
+    // the owner checks follow the new owner
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Reset {
+                counter: Uint128::new(10),
+                name: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: new_owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        new_owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Reset {
+            counter: Uint128::new(10),
+            name: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: OwnershipResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(
+        resp,
+        OwnershipResp {
+            owner: Some(new_owner),
+            pending_owner: None,
+            pending_expiry: None,
+        }
+    );
+}
+
+#[test]
+fn ownership_transfer_accepted_by_other() {
+    let owner = Addr::unchecked("owner");
+    let new_owner = Addr::unchecked("new_owner");
+    let member = Addr::unchecked("member");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        owner,
+        contract_addr.clone(),
+        &ExecMsg::ProposeOwner {
+            new_owner: new_owner.to_string(),
+            expiry: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(member, contract_addr, &ExecMsg::AcceptOwnership {}, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::NotPendingOwner {
+            pending_owner: new_owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn expired_ownership_transfer() {
+    let owner = Addr::unchecked("owner");
+    let new_owner = Addr::unchecked("new_owner");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let expiry = Expiration::AtHeight(app.block_info().height + 10);
+    app.execute_contract(
+        owner,
+        contract_addr.clone(),
+        &ExecMsg::ProposeOwner {
+            new_owner: new_owner.to_string(),
+            expiry: Some(expiry),
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.update_block(|block| block.height += 10);
+
+    let err = app
+        .execute_contract(new_owner, contract_addr, &ExecMsg::AcceptOwnership {}, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::TransferExpired {}, err.downcast().unwrap());
+}
+
+#[test]
+fn cancel_ownership_transfer() {
+    let owner = Addr::unchecked("owner");
+    let new_owner = Addr::unchecked("new_owner");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ProposeOwner {
+            new_owner: new_owner.to_string(),
+            expiry: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner,
+        contract_addr.clone(),
+        &ExecMsg::CancelOwnershipTransfer {},
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(new_owner, contract_addr, &ExecMsg::AcceptOwnership {}, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::NoPendingOwner {}, err.downcast().unwrap());
+}
+
+#[test]
+fn renounce_ownership() {
+    let owner = Addr::unchecked("owner");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RenounceOwnership {},
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(owner, contract_addr.clone(), &ExecMsg::Withdraw {}, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::MissingRole {
+            role: "withdrawer".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    let resp: OwnershipResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(
+        resp,
+        OwnershipResp {
+            owner: None,
+            pending_owner: None,
+            pending_expiry: None,
+        }
+    );
+}
+
+#[test]
+fn granted_roles() {
+    let owner = Addr::unchecked("owner");
+    let resetter = Addr::unchecked("resetter");
+    let withdrawer = Addr::unchecked("withdrawer");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(10, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    for (role, address) in [(Role::Resetter, &resetter), (Role::Withdrawer, &withdrawer)] {
+        app.execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::GrantRole {
+                role,
+                address: address.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    app.execute_contract(
+        resetter.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Reset {
+            counter: Uint128::new(5),
+            name: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(5)
+        }
+    );
+
+    // roles are separate - the resetter can't withdraw
+    let err = app
+        .execute_contract(
+            resetter.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Withdraw {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        withdrawer.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&withdrawer).unwrap(),
+        coins(10, "atom")
+    );
+
+    let resp: RolesResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Roles {
+                address: resetter.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        RolesResp {
+            roles: vec![Role::Resetter]
+        }
+    );
+
+    app.execute_contract(
+        owner,
+        contract_addr.clone(),
+        &ExecMsg::RevokeRole {
+            role: Role::Resetter,
+            address: resetter.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        resetter,
+        contract_addr,
+        &ExecMsg::Reset {
+            counter: Uint128::new(0),
+            name: None,
+        },
+        &[],
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn role_members() {
+    let owner = Addr::unchecked("owner");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    for member in ["member1", "member2", "member3"] {
+        app.execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::GrantRole {
+                role: Role::Pauser,
+                address: member.to_owned(),
+            },
+            &[],
+        )
+        .unwrap();
+    }
+
+    let resp: RoleMembersResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::RoleMembers {
+                role: Role::Pauser,
+                start_after: None,
+                limit: Some(2),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.members,
+        vec![Addr::unchecked("member1"), Addr::unchecked("member2")]
+    );
+
+    let resp: RoleMembersResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::RoleMembers {
+                role: Role::Pauser,
+                start_after: Some("member2".to_owned()),
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.members, vec![Addr::unchecked("member3")]);
+
+    let resp: RoleMembersResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::RoleMembers {
+                role: Role::Resetter,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.members, Vec::<Addr>::new());
+}
+
+#[test]
+fn unauthorized_grant_role() {
+    let owner = Addr::unchecked("owner");
+    let member = Addr::unchecked("member");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let err = app
+        .execute_contract(
+            member.clone(),
+            contract_addr,
+            &ExecMsg::GrantRole {
+                role: Role::Withdrawer,
+                address: member.to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn donor_ledger() {
+    let owner = Addr::unchecked("owner");
+    let donor1 = Addr::unchecked("donor1");
+    let donor2 = Addr::unchecked("donor2");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &donor1, vec![coin(30, "atom"), coin(5, "osmo")])
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &donor2, coins(5, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        donor1.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &[coin(10, "atom"), coin(5, "osmo")],
+    )
+    .unwrap();
+    app.execute_contract(
+        donor1.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(20, "atom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        donor2.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(5, "atom"),
+    )
+    .unwrap();
+
+    let resp: DonorResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Donor {
+                address: donor1.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp,
+        DonorResp {
+            address: donor1.clone(),
+            qualifying: coins(30, "atom"),
+            non_qualifying: coins(5, "osmo"),
+            matched: vec![],
+        }
+    );
+
+    let resp: DonorsResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Donors {
+                start_after: Some(donor1.to_string()),
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.donors,
+        vec![DonorResp {
+            address: donor2,
+            qualifying: vec![],
+            non_qualifying: coins(5, "atom"),
+            matched: vec![],
+        }]
+    );
+
+    // addresses which never donated have empty totals
+    let resp: DonorResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Donor {
+                address: owner.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.qualifying, vec![]);
+    assert_eq!(resp.non_qualifying, vec![]);
+}
+
+#[test]
+fn multiple_accepted_denoms() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(
+                storage,
+                &sender,
+                vec![coin(10, "atom"), coin(100, "osmo"), coin(50, "uusdc")],
+            )
+            .unwrap();
+    });
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: vec![coin(10, "atom"), coin(100, "osmo")],
+            ..Default::default()
+        },
+    );
+
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "osmo"),
+    )
+    .unwrap();
+
+    // uusdc is not accepted yet, so it doesn't count
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(25, "uusdc"),
+    )
+    .unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(1)
+        }
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::AddAcceptedDenom {
+            minimal_donation: coin(25, "uusdc"),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RemoveAcceptedDenom {
+            denom: "osmo".to_owned(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: AcceptedDenomsResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::AcceptedDenoms {})
+        .unwrap();
+    assert_eq!(resp.denoms, vec![coin(10, "atom"), coin(25, "uusdc")]);
+
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(25, "uusdc"),
+    )
+    .unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(2)
+        }
+    );
+}
+
+#[test]
+fn zero_minimal_donation() {
+    let owner = Addr::unchecked("owner");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let err = app
+        .execute_contract(
+            owner,
+            contract_addr,
+            &ExecMsg::AddAcceptedDenom {
+                minimal_donation: coin(0, "osmo"),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::InvalidMinimalDonation {
+            denom: "osmo".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+}
+
+// instantiates the contract accepting 10atom and 100osmo with the given policy,
+// donates the funds, and returns the counter value
+fn count_donation(policy: CountingPolicy, funds: &[Coin]) -> u128 {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, funds.to_vec())
+            .unwrap();
+    });
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: vec![coin(10, "atom"), coin(100, "osmo")],
+            counting_policy: policy,
+            ..Default::default()
+        },
+    );
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        funds,
+    )
+    .unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    resp.value.u128()
+}
+
+#[test]
+fn count_once_per_message() {
+    let value = count_donation(
+        CountingPolicy::OncePerMessage,
+        &[coin(35, "atom"), coin(100, "osmo")],
+    );
+    assert_eq!(value, 1);
+}
+
+#[test]
+fn count_once_per_coin() {
+    let value = count_donation(
+        CountingPolicy::OncePerCoin,
+        &[coin(35, "atom"), coin(100, "osmo"), coin(50, "uusdc")],
+    );
+    assert_eq!(value, 2);
+}
+
+#[test]
+fn count_proportional() {
+    // 3 units of atom and 2 units of osmo, the remainders are not counted
+    let value = count_donation(
+        CountingPolicy::Proportional,
+        &[coin(35, "atom"), coin(250, "osmo")],
+    );
+    assert_eq!(value, 5);
+}
+
+#[test]
+fn count_tiered() {
+    let policy = CountingPolicy::Tiered {
+        tiers: vec![
+            Tier {
+                threshold: Uint128::new(1),
+                increment: 1,
+            },
+            Tier {
+                threshold: Uint128::new(5),
+                increment: 10,
+            },
+            Tier {
+                threshold: Uint128::new(10),
+                increment: 25,
+            },
+        ],
+    };
+
+    // 7 units of atom reach the second tier, 1 unit of osmo the first one
+    let value = count_donation(policy.clone(), &[coin(70, "atom"), coin(100, "osmo")]);
+    assert_eq!(value, 11);
+
+    let value = count_donation(policy, &coins(100, "atom"));
+    assert_eq!(value, 25);
+}
+
+#[test]
+fn change_counting_policy() {
+    let owner = Addr::unchecked("owner");
+    let member = Addr::unchecked("member");
+
+    let mut app = App::default();
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let err = app
+        .execute_contract(
+            member,
+            contract_addr.clone(),
+            &ExecMsg::SetCountingPolicy {
+                policy: CountingPolicy::Proportional,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    // unsorted tiers are rejected
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::SetCountingPolicy {
+                policy: CountingPolicy::Tiered {
+                    tiers: vec![
+                        Tier {
+                            threshold: Uint128::new(5),
+                            increment: 5,
+                        },
+                        Tier {
+                            threshold: Uint128::new(2),
+                            increment: 2,
+                        },
+                    ],
+                },
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::InvalidTiers {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        owner,
+        contract_addr.clone(),
+        &ExecMsg::SetCountingPolicy {
+            policy: CountingPolicy::Proportional,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: CountingPolicy = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::CountingPolicy {})
+        .unwrap();
+    assert_eq!(resp, CountingPolicy::Proportional);
+}
+
+#[test]
+fn refund_non_qualifying() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, vec![coin(25, "atom"), coin(5, "osmo")])
+            .unwrap();
+    });
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            refund_mode: RefundMode::NonQualifying,
+            ..Default::default()
+        },
+    );
+
+    let resp = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &[coin(25, "atom"), coin(5, "osmo")],
+        )
+        .unwrap();
+
+    let wasm = resp.events.iter().find(|ev| ev.ty == "wasm").unwrap();
+    assert!(wasm
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "kept" && attr.value == "25atom"));
+    assert!(wasm
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "refunded" && attr.value == "5osmo"));
+
+    assert_eq!(
+        app.wrap().query_all_balances(&sender).unwrap(),
+        coins(5, "osmo")
+    );
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        coins(25, "atom")
+    );
+
+    // refunded funds are not recorded as donated
+    let resp: DonorResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Donor {
+                address: sender.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.qualifying, coins(25, "atom"));
+    assert_eq!(resp.non_qualifying, vec![]);
+}
+
+#[test]
+fn refund_excess() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, vec![coin(50, "atom"), coin(5, "osmo")])
+            .unwrap();
+    });
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            refund_mode: RefundMode::NonQualifyingAndExcess,
+            ..Default::default()
+        },
+    );
+
+    // counted once, so everything above the minimal donation goes back
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &[coin(25, "atom"), coin(5, "osmo")],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        coins(10, "atom")
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetCountingPolicy {
+            policy: CountingPolicy::Proportional,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // two full units are counted, only the remainder goes back
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(25, "atom"),
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        coins(30, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_all_balances(&sender).unwrap(),
+        vec![coin(20, "atom"), coin(5, "osmo")]
+    );
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(3)
+        }
+    );
+}
+
+#[test]
+fn strict_donations() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, vec![coin(20, "atom"), coin(5, "osmo")])
+            .unwrap();
+    });
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            strict: true,
+            ..Default::default()
+        },
+    );
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::NoFunds {}, err.downcast().unwrap());
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &[coin(10, "atom"), coin(5, "osmo")],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::InvalidDenom {
+            denom: "osmo".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &coins(5, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::BelowMinimum {
+            required: coin(10, "atom"),
+            sent: coin(5, "atom"),
+        },
+        err.downcast().unwrap()
+    );
+
+    // funds can't be sent with messages not expecting them
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Reset {
+                counter: Uint128::new(0),
+                name: None,
+            },
+            &coins(5, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::UnexpectedFunds {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(1)
+        }
+    );
+}
+
+// opens a campaign for 100atom ending 1000 seconds from now
+fn open_campaign(app: &mut App, owner: &Addr, contract_addr: &Addr) {
+    let deadline = app.block_info().time.plus_seconds(1000);
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::OpenCampaign {
+            goal: coins(100, "atom"),
+            deadline,
+        },
+        &[],
+    )
+    .unwrap();
+}
+
+#[test]
+fn successful_campaign() {
+    let owner = Addr::unchecked("owner");
+    let donor1 = Addr::unchecked("donor1");
+    let donor2 = Addr::unchecked("donor2");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &donor1, coins(60, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &donor2, coins(50, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    open_campaign(&mut app, &owner, &contract_addr);
+
+    for (donor, amount) in [(&donor1, 60), (&donor2, 50)] {
+        app.execute_contract(
+            donor.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+    }
+
+    let resp: CampaignResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Campaign { campaign_id: None })
+        .unwrap();
+    assert_eq!(resp.campaign_id, 1);
+    assert_eq!(resp.raised, coins(110, "atom"));
+    assert_eq!(resp.status, CampaignStatus::Open);
+
+    // escrowed funds can't be withdrawn
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.wrap().query_all_balances(&owner).unwrap(), vec![]);
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::ClaimCampaignFunds { campaign_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::CampaignOpen { campaign_id: 1 },
+        err.downcast().unwrap()
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(1000));
+
+    let resp: CampaignResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Campaign {
+                campaign_id: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.status, CampaignStatus::Succeeded);
+
+    let err = app
+        .execute_contract(donor1, contract_addr.clone(), &ExecMsg::ClaimRefund {}, &[])
+        .unwrap_err();
+    assert_eq!(ContractError::NothingToRefund {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ClaimCampaignFunds { campaign_id: 1 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(110, "atom")
+    );
+
+    let err = app
+        .execute_contract(
+            owner,
+            contract_addr.clone(),
+            &ExecMsg::ClaimCampaignFunds { campaign_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::CampaignClaimed { campaign_id: 1 },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn failed_campaign() {
+    let owner = Addr::unchecked("owner");
+    let donor = Addr::unchecked("donor");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &donor, coins(50, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    // donations made before the campaign are not escrowed
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(20, "atom"),
+    )
+    .unwrap();
+
+    open_campaign(&mut app, &owner, &contract_addr);
+
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(30, "atom"),
+    )
+    .unwrap();
+
+    let resp: ContributionResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Contribution {
+                campaign_id: 1,
+                address: donor.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.contributed, coins(30, "atom"));
+
+    // another campaign can't be opened while this one runs
+    let deadline = app.block_info().time.plus_seconds(2000);
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::OpenCampaign {
+                goal: coins(100, "atom"),
+                deadline,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::CampaignOpen { campaign_id: 1 },
+        err.downcast().unwrap()
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(1000));
+
+    let resp: CampaignResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Campaign { campaign_id: None })
+        .unwrap();
+    assert_eq!(resp.status, CampaignStatus::Failed);
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::ClaimCampaignFunds { campaign_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::GoalNotMet { campaign_id: 1 },
+        err.downcast().unwrap()
+    );
+
+    // the contribution counted like any donation while the campaign was open
+    let badge_units = |app: &App| -> Uint128 {
+        let resp: NftInfoResp = app
+            .wrap()
+            .query_wasm_smart(
+                &contract_addr,
+                &QueryMsg::NftInfo {
+                    token_id: donor.to_string(),
+                },
+            )
+            .unwrap();
+        resp.extension.donated_units
+    };
+    assert_eq!(counter_value(&app, &contract_addr, None), 2);
+    assert_eq!(
+        leaderboard(&app, &contract_addr, "atom"),
+        [("donor".to_owned(), 50)]
+    );
+    assert_eq!(badge_units(&app), Uint128::new(5));
+
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ClaimRefund {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&donor).unwrap(),
+        coins(30, "atom")
+    );
+
+    // the refunded contribution doesn't count anymore, only the donation before the campaign does
+    assert_eq!(counter_value(&app, &contract_addr, None), 1);
+    assert_eq!(
+        leaderboard(&app, &contract_addr, "atom"),
+        [("donor".to_owned(), 20)]
+    );
+    assert_eq!(badge_units(&app), Uint128::new(2));
+    let resp: DonorResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Donor {
+                address: donor.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.qualifying, coins(20, "atom"));
+
+    // only the donation made outside of the campaign is left to withdraw
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(20, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        vec![]
+    );
+}
+
+#[test]
+fn donation_memos() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(50, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    for memo in ["first", "second", "third"] {
+        app.execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: Some(memo.to_owned()),
+                name: None,
+            },
+            &coins(10, "atom"),
+        )
+        .unwrap();
+        app.update_block(|block| block.time = block.time.plus_seconds(5));
+    }
+
+    // donations which don't qualify don't post memos, the donor is told the memo was dropped
+    let resp = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: Some("spam".to_owned()),
+                name: None,
+            },
+            &coins(5, "atom"),
+        )
+        .unwrap();
+    let wasm = resp.events.iter().find(|ev| ev.ty == "wasm").unwrap();
+    assert!(wasm
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "memo_dropped" && attr.value == "true"));
+    assert!(!wasm.attributes.iter().any(|attr| attr.key == "message_id"));
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: Some("x".repeat(281)),
+                name: None,
+            },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::MemoTooLong { max: 280 },
+        err.downcast().unwrap()
+    );
+
+    let resp: MessagesResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Messages {
+                start_after: None,
+                limit: Some(2),
+                order: None,
+            },
+        )
+        .unwrap();
+    let memos: Vec<_> = resp.messages.iter().map(|m| m.memo.as_str()).collect();
+    assert_eq!(memos, ["first", "second"]);
+    assert_eq!(resp.messages[0].sender, sender);
+    assert_eq!(resp.messages[1].time, resp.messages[0].time.plus_seconds(5));
+
+    let resp: MessagesResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Messages {
+                start_after: Some(3),
+                limit: None,
+                order: Some(SortOrder::Descending),
+            },
+        )
+        .unwrap();
+    let ids: Vec<_> = resp.messages.iter().map(|m| m.id).collect();
+    assert_eq!(ids, [2, 1]);
+}
+
+fn leaderboard(app: &App, contract_addr: &Addr, denom: &str) -> Vec<(String, u128)> {
+    let resp: LeaderboardResp = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &QueryMsg::Leaderboard {
+                denom: denom.to_owned(),
+                limit: None,
+            },
+        )
+        .unwrap();
+
+    resp.entries
+        .into_iter()
+        .map(|LeaderboardEntry { address, amount }| (address.into_string(), amount.u128()))
+        .collect()
+}
+
+#[test]
+fn donor_leaderboard() {
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let carol = Addr::unchecked("carol");
+
+    let mut app = App::new(|router, _api, storage| {
+        for donor in [&alice, &bob, &carol] {
+            router
+                .bank
+                .init_balance(storage, donor, vec![coin(100, "atom"), coin(100, "osmo")])
+                .unwrap();
+        }
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    for (donor, amount) in [(&alice, 20), (&bob, 30), (&carol, 20)] {
+        app.execute_contract(
+            donor.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+    }
+
+    // ties are ordered by the address
+    assert_eq!(
+        leaderboard(&app, &contract_addr, "atom"),
+        [
+            ("bob".to_owned(), 30),
+            ("alice".to_owned(), 20),
+            ("carol".to_owned(), 20)
+        ]
+    );
+
+    // carol overtakes bob, not qualifying donations and other denoms don't count
+    app.execute_contract(
+        carol.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &[coin(15, "atom"), coin(100, "osmo")],
+    )
+    .unwrap();
+    app.execute_contract(
+        alice.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(5, "atom"),
+    )
+    .unwrap();
+
+    assert_eq!(
+        leaderboard(&app, &contract_addr, "atom"),
+        [
+            ("carol".to_owned(), 35),
+            ("bob".to_owned(), 30),
+            ("alice".to_owned(), 20)
+        ]
+    );
+    assert_eq!(leaderboard(&app, &contract_addr, "osmo"), []);
+}
+
+#[test]
+fn soulbound_badges() {
+    let owner = Addr::unchecked("owner");
+    let donor = Addr::unchecked("donor");
+    let member = Addr::unchecked("member");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &donor, coins(110, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &member, coins(5, "atom"))
+            .unwrap();
+    });
+
+    let contract_id = app.store_code(counting_contract());
+    for (silver, gold) in [(0, 100), (100, 100), (200, 100)] {
+        let err = app
+            .instantiate_contract(
+                contract_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    badge_thresholds: BadgeThresholds {
+                        silver: Uint128::new(silver),
+                        gold: Uint128::new(gold),
+                    },
+                    ..Default::default()
+                },
+                &[],
+                "Counting contract",
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(
+            ContractError::InvalidBadgeThresholds {},
+            err.downcast().unwrap()
+        );
+    }
+
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let resp = app
+        .execute_contract(
+            donor.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &coins(10, "atom"),
+        )
+        .unwrap();
+    let wasm = resp.events.iter().find(|ev| ev.ty == "wasm").unwrap();
+    assert!(wasm
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "badge_tier" && attr.value == "bronze"));
+
+    let resp: OwnerOfResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::OwnerOf {
+                token_id: donor.to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.owner, donor.as_str());
+    assert_eq!(resp.approvals, vec![]);
+
+    let resp: TokensResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Tokens {
+                owner: donor.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.tokens, vec![donor.to_string()]);
+
+    // 10 units in total reach the default silver threshold
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(95, "atom"),
+    )
+    .unwrap();
+
+    let resp: NftInfoResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::NftInfo {
+                token_id: donor.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.extension,
+        BadgeExtension {
+            tier: BadgeTier::Silver,
+            donated_units: Uint128::new(10),
+        }
+    );
+
+    let err = app
+        .execute_contract(
+            donor.clone(),
+            contract_addr.clone(),
+            &ExecMsg::TransferNft {
+                recipient: member.to_string(),
+                token_id: donor.to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::Soulbound {}, err.downcast().unwrap());
+
+    // donations below the minimum don't earn a badge
+    app.execute_contract(
+        member.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(5, "atom"),
+    )
+    .unwrap();
+
+    let resp: TokensResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Tokens {
+                owner: member.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.tokens, Vec::<String>::new());
+}
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+    Box::new(contract)
+}
+
+// stores and instantiates a cw20-base token with the given initial balances
+fn instantiate_cw20(app: &mut App, owner: &Addr, symbol: &str, balances: Vec<Cw20Coin>) -> Addr {
+    let contract_id = app.store_code(cw20_contract());
+
+    app.instantiate_contract(
+        contract_id,
+        owner.clone(),
+        &cw20_base::msg::InstantiateMsg {
+            name: format!("{symbol} token"),
+            symbol: symbol.to_owned(),
+            decimals: 6,
+            initial_balances: balances,
+            mint: None,
+            marketing: None,
+        },
+        &[],
+        "Cw20 token",
+        None,
+    )
+    .unwrap()
+}
+
+fn cw20_balance(app: &App, token: &Addr, address: &Addr) -> u128 {
+    let resp: BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            token,
+            &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )
+        .unwrap();
+    resp.balance.u128()
+}
+
+// sends the tokens to the contract with the donate hook
+fn donate_cw20(
+    app: &mut App,
+    donor: &Addr,
+    token: &Addr,
+    contract_addr: &Addr,
+    amount: u128,
+    memo: Option<&str>,
+) -> AnyResult<AppResponse> {
+    app.execute_contract(
+        donor.clone(),
+        token.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: contract_addr.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_json_binary(&ReceiveMsg::Donate {
+                memo: memo.map(str::to_owned),
+                name: None,
+            })
+            .unwrap(),
+        },
+        &[],
+    )
+}
+
+#[test]
+fn cw20_donations() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::default();
+    let balances = vec![Cw20Coin {
+        address: sender.to_string(),
+        amount: Uint128::new(100),
+    }];
+    let token = instantiate_cw20(&mut app, &owner, "TKN", balances.clone());
+    let other_token = instantiate_cw20(&mut app, &owner, "OTHER", balances);
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            accepted_cw20s: vec![Cw20Coin {
+                address: token.to_string(),
+                amount: Uint128::new(10),
+            }],
+            ..Default::default()
+        },
+    );
+
+    let resp: AcceptedCw20sResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::AcceptedCw20s {})
+        .unwrap();
+    assert_eq!(
+        resp.tokens,
+        vec![Cw20Coin {
+            address: token.to_string(),
+            amount: Uint128::new(10),
+        }]
+    );
+
+    donate_cw20(
+        &mut app,
+        &sender,
+        &token,
+        &contract_addr,
+        20,
+        Some("tokens!"),
+    )
+    .unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(1)
+        }
+    );
+    assert_eq!(cw20_balance(&app, &token, &contract_addr), 20);
+    assert_eq!(cw20_balance(&app, &token, &sender), 80);
+
+    // the token address is used as the denom in the donor ledger
+    let resp: DonorResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Donor {
+                address: sender.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.qualifying, coins(20, token.as_str()));
+
+    let resp: MessagesResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Messages {
+                start_after: None,
+                limit: None,
+                order: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.messages[0].sender, sender);
+    assert_eq!(resp.messages[0].memo, "tokens!");
+
+    // below the minimum, the tokens are kept but not counted
+    donate_cw20(&mut app, &sender, &token, &contract_addr, 5, None).unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(1)
+        }
+    );
+    assert_eq!(cw20_balance(&app, &token, &contract_addr), 25);
+
+    // tokens which are not accepted are rejected, so the transfer is reverted
+    let err = donate_cw20(&mut app, &sender, &other_token, &contract_addr, 20, None).unwrap_err();
+    assert_eq!(
+        ContractError::InvalidDenom {
+            denom: other_token.to_string()
+        },
+        err.downcast().unwrap()
+    );
+    assert_eq!(cw20_balance(&app, &other_token, &sender), 100);
+}
+
+#[test]
+fn accepted_cw20_refunds() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::default();
+    let token = instantiate_cw20(
+        &mut app,
+        &owner,
+        "TKN",
+        vec![Cw20Coin {
+            address: sender.to_string(),
+            amount: Uint128::new(100),
+        }],
+    );
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            counting_policy: CountingPolicy::Proportional,
+            refund_mode: RefundMode::NonQualifyingAndExcess,
+            ..Default::default()
+        },
+    );
+
+    // only the config admin can accept new tokens
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::AddAcceptedCw20 {
+                address: token.to_string(),
+                minimal_donation: Uint128::new(10),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::AddAcceptedCw20 {
+            address: token.to_string(),
+            minimal_donation: Uint128::new(10),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // the excess above the counted amount is transferred back
+    let resp = donate_cw20(&mut app, &sender, &token, &contract_addr, 25, None).unwrap();
+    // the first wasm event is emitted by the token contract
+    let wasm = resp
+        .events
+        .iter()
+        .filter(|ev| ev.ty == "wasm")
+        .find(|ev| ev.attributes.iter().any(|attr| attr.value == "donate_cw20"))
+        .unwrap();
+    assert!(wasm
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "refunded" && attr.value == format!("5{token}")));
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(2)
+        }
+    );
+    assert_eq!(cw20_balance(&app, &token, &contract_addr), 20);
+    assert_eq!(cw20_balance(&app, &token, &sender), 80);
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RemoveAcceptedCw20 {
+            address: token.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: AcceptedCw20sResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::AcceptedCw20s {})
+        .unwrap();
+    assert_eq!(resp.tokens, vec![]);
+
+    let err = donate_cw20(&mut app, &sender, &token, &contract_addr, 10, None).unwrap_err();
+    assert_eq!(
+        ContractError::InvalidDenom {
+            denom: token.to_string()
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn cw20_withdrawals() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let receiver = Addr::unchecked("receiver");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(10, "atom"))
+            .unwrap();
+    });
+    let token = instantiate_cw20(
+        &mut app,
+        &owner,
+        "TKN",
+        vec![Cw20Coin {
+            address: sender.to_string(),
+            amount: Uint128::new(100),
+        }],
+    );
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            accepted_cw20s: vec![Cw20Coin {
+                address: token.to_string(),
+                amount: Uint128::new(10),
+            }],
+            ..Default::default()
+        },
+    );
+
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    donate_cw20(&mut app, &sender, &token, &contract_addr, 30, None).unwrap();
+
+    // the limits apply per token, the native funds not listed are kept
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::WithdrawTo {
+            receiver: receiver.to_string(),
+            funds: vec![],
+            cw20_funds: vec![Cw20Coin {
+                address: token.to_string(),
+                amount: Uint128::new(10),
+            }],
+        },
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(cw20_balance(&app, &token, &receiver), 10);
+    assert_eq!(cw20_balance(&app, &token, &contract_addr), 20);
+    assert_eq!(app.wrap().query_all_balances(&receiver).unwrap(), vec![]);
+
+    // the same way a native only limit keeps all the tokens
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::WithdrawTo {
+            receiver: receiver.to_string(),
+            funds: coins(5, "atom"),
+            cw20_funds: vec![],
+        },
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(cw20_balance(&app, &token, &receiver), 10);
+    assert_eq!(cw20_balance(&app, &token, &contract_addr), 20);
+    assert_eq!(
+        app.wrap().query_all_balances(&receiver).unwrap(),
+        coins(5, "atom")
+    );
+
+    // tokens which are no longer accepted can still be withdrawn
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RemoveAcceptedCw20 {
+            address: token.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(cw20_balance(&app, &token, &owner), 20);
+    assert_eq!(cw20_balance(&app, &token, &contract_addr), 0);
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(5, "atom")
+    );
+
+    // with nothing left, withdrawing sends nothing
+    let resp = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Withdraw {},
+            &[],
+        )
+        .unwrap();
+    assert!(!resp.events.iter().any(|ev| ev.ty == "transfer"));
+    assert_eq!(
+        resp.events.iter().filter(|ev| ev.ty == "execute").count(),
+        1
+    );
+}
+
+#[test]
+fn matching_pools() {
+    let owner = Addr::unchecked("owner");
+    let sponsor = Addr::unchecked("sponsor");
+    let donor = Addr::unchecked("donor");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sponsor, coins(100, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &donor, coins(20, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    // matching two atoms for every donated one, up to 30atom in total
+    let deadline = app.block_info().time.plus_seconds(1000);
+    let sponsor_msg = ExecMsg::SponsorMatching {
+        ratio: Decimal::percent(200),
+        cap: Uint128::new(30),
+        deadline,
+    };
+    app.execute_contract(
+        sponsor.clone(),
+        contract_addr.clone(),
+        &sponsor_msg,
+        &coins(50, "atom"),
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            sponsor.clone(),
+            contract_addr.clone(),
+            &sponsor_msg,
+            &coins(50, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::MatchingPoolExists {
+            sponsor: sponsor.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    // the second donation reaches the cap
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    // matched funds are kept apart from what the donor gave
+    let resp: DonorResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Donor {
+                address: donor.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.qualifying, coins(20, "atom"));
+    assert_eq!(resp.matched, coins(30, "atom"));
+
+    let resp: MatchingPoolsResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::MatchingPools {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.pools,
+        vec![MatchingPoolResp {
+            sponsor: sponsor.clone(),
+            remaining: coin(20, "atom"),
+            ratio: Decimal::percent(200),
+            cap: Uint128::new(30),
+            matched: Uint128::new(30),
+            deadline,
+        }]
+    );
+
+    // what is left of the pool can't be withdrawn by the owner
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(50, "atom")
+    );
+
+    let err = app
+        .execute_contract(
+            sponsor.clone(),
+            contract_addr.clone(),
+            &ExecMsg::ReclaimMatchingPool {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::MatchingPoolActive { deadline },
+        err.downcast().unwrap()
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(1000));
+
+    app.execute_contract(
+        sponsor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ReclaimMatchingPool {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&sponsor).unwrap(),
+        coins(70, "atom")
+    );
+
+    let err = app
+        .execute_contract(
+            sponsor.clone(),
+            contract_addr.clone(),
+            &ExecMsg::ReclaimMatchingPool {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::NothingToReclaim {}, err.downcast().unwrap());
+
+    let resp: MatchingPoolResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::MatchingPool {
+                sponsor: sponsor.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.remaining, coin(0, "atom"));
+    assert_eq!(resp.matched, Uint128::new(30));
+}
+
+#[test]
+fn large_matched_donation() {
+    let owner = Addr::unchecked("owner");
+    let sponsor = Addr::unchecked("sponsor");
+    let donor = Addr::unchecked("donor");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sponsor, coins(100, "atom"))
+            .unwrap();
+        router
+            .bank
+            .init_balance(storage, &donor, coins(u128::MAX / 2, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let deadline = app.block_info().time.plus_seconds(1000);
+    app.execute_contract(
+        sponsor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SponsorMatching {
+            ratio: Decimal::percent(1000),
+            cap: Uint128::new(100),
+            deadline,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    // matching the donation would overflow, so it just takes what is left of the pool
+    app.execute_contract(
+        donor,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(u128::MAX / 2, "atom"),
+    )
+    .unwrap();
+
+    let resp: MatchingPoolResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::MatchingPool {
+                sponsor: sponsor.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.remaining, coin(0, "atom"));
+}
+
+// the pausable messages are listed by hand, so the list is checked against the message schema
+// every message except pausing and unpausing itself has to be on the list
+#[test]
+fn all_messages_pausable() {
+    let schema = schema_for!(ExecMsg);
+    let mut messages: Vec<String> = schema
+        .schema
+        .subschemas
+        .unwrap()
+        .one_of
+        .unwrap()
+        .into_iter()
+        .flat_map(|variant| match variant {
+            Schema::Object(variant) => variant.object.unwrap().required,
+            Schema::Bool(_) => Default::default(),
+        })
+        .filter(|message| message != "pause" && message != "unpause")
+        .collect();
+    messages.sort();
+
+    let mut pausable = ExecMsg::PAUSABLE.map(str::to_owned).to_vec();
+    pausable.sort();
+
+    assert_eq!(messages, pausable);
+}
+
+#[test]
+fn pause_switch() {
+    let owner = Addr::unchecked("owner");
+    let pauser = Addr::unchecked("pauser");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(20, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let err = app
+        .execute_contract(
+            pauser.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Pause { messages: None },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::GrantRole {
+            role: Role::Pauser,
+            address: pauser.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            pauser.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Pause {
+                messages: Some(vec!["Donate".to_owned()]),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::UnknownMessage {
+            message: "Donate".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        pauser.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Pause {
+            messages: Some(vec!["donate".to_owned(), "withdraw_to".to_owned()]),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: PauseStatusResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::PauseStatus {})
+        .unwrap();
+    assert_eq!(
+        resp,
+        PauseStatusResp {
+            all: false,
+            messages: vec!["donate".to_owned(), "withdraw_to".to_owned()],
+        }
+    );
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &coins(10, "atom"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Paused {
+            message: "donate".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    // other messages are not affected
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        pauser.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Pause { messages: None },
+        &[],
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Reset {
+                counter: Uint128::new(0),
+                name: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Paused {
+            message: "reset".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(pauser, contract_addr.clone(), &ExecMsg::Unpause {}, &[])
+        .unwrap();
+
+    let resp: PauseStatusResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::PauseStatus {})
+        .unwrap();
+    assert_eq!(
+        resp,
+        PauseStatusResp {
+            all: false,
+            messages: vec![],
+        }
+    );
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Value { name: None })
+        .unwrap();
+    assert_eq!(
+        resp,
+        ValueResp {
+            value: Uint128::new(1)
+        }
+    );
+}
+
+fn payee(address: &Addr, share: u16) -> Payee {
+    Payee {
+        address: address.to_string(),
+        share,
+    }
+}
+
+#[test]
+fn payment_splitter() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let carol = Addr::unchecked("carol");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(110, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::SetPayees {
+                payees: vec![payee(&alice, 5000), payee(&bob, 4999)],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::InvalidShares {}, err.downcast().unwrap());
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetPayees {
+            payees: vec![payee(&alice, 3333), payee(&bob, 3333), payee(&carol, 3334)],
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Distribute {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::NotPayee {}, err.downcast().unwrap());
+
+    // 33atom for every payee, the first one gets the remaining 1atom
+    app.execute_contract(
+        bob.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Distribute {},
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        app.wrap().query_all_balances(&alice).unwrap(),
+        coins(34, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_all_balances(&bob).unwrap(),
+        coins(33, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_all_balances(&carol).unwrap(),
+        coins(33, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        vec![]
+    );
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        carol.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Distribute {},
+        &[],
+    )
+    .unwrap();
+
+    let resp: PayeesResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Payees {})
+        .unwrap();
+    assert_eq!(
+        resp.payees,
+        vec![
+            PayeeResp {
+                address: alice.clone(),
+                share: 3333,
+                paid: coins(38, "atom"),
+            },
+            PayeeResp {
+                address: bob.clone(),
+                share: 3333,
+                paid: coins(36, "atom"),
+            },
+            PayeeResp {
+                address: carol.clone(),
+                share: 3334,
+                paid: coins(36, "atom"),
+            },
+        ]
+    );
+
+    // payouts stay queryable after the payee is removed
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetPayees {
+            payees: vec![payee(&alice, 10000)],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: PayoutsResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Payouts {
+                address: bob.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.paid, coins(36, "atom"));
+}
+
+fn vested(app: &App, contract_addr: &Addr) -> Vec<VestedDenom> {
+    let resp: VestedResp = app
+        .wrap()
+        .query_wasm_smart(contract_addr, &QueryMsg::Vested {})
+        .unwrap();
+    resp.denoms
+}
+
+#[test]
+fn linear_vesting() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(200, "atom"))
+            .unwrap();
+    });
+    let start = app.block_info().time;
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            vesting: Some(VestingSchedule::Linear {
+                start,
+                end: start.plus_seconds(1000),
+            }),
+            ..Default::default()
+        },
+    );
+
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    app.update_block(|block| block.time = block.time.plus_seconds(250));
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(25, "atom")
+    );
+
+    assert_eq!(
+        vested(&app, &contract_addr),
+        vec![VestedDenom {
+            denom: "atom".to_owned(),
+            vested: Uint128::new(25),
+            withdrawn: Uint128::new(25),
+            locked: Uint128::new(75),
+        }]
+    );
+
+    // later donations are vested together with the earlier ones
+    app.update_block(|block| block.time = block.time.plus_seconds(250));
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(100, "atom")
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(1000));
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(200, "atom")
+    );
+
+    assert_eq!(
+        vested(&app, &contract_addr),
+        vec![VestedDenom {
+            denom: "atom".to_owned(),
+            vested: Uint128::new(200),
+            withdrawn: Uint128::new(200),
+            locked: Uint128::zero(),
+        }]
+    );
+}
+
+#[test]
+fn cliff_vesting() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let now = app.block_info().time;
+
+    let contract_id = app.store_code(counting_contract());
+    let err = app
+        .instantiate_contract(
+            contract_id,
+            owner.clone(),
+            &InstantiateMsg {
+                vesting: Some(VestingSchedule::Linear {
+                    start: now,
+                    end: now,
+                }),
+                ..Default::default()
+            },
+            &[],
+            "Counting contract",
+            None,
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::InvalidVesting {}, err.downcast().unwrap());
+
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::new(0),
+            minimal_donations: coins(10, "atom"),
+            vesting: Some(VestingSchedule::Cliff {
+                at: now.plus_seconds(100),
+            }),
+            ..Default::default()
+        },
+    );
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    // nothing is released before the cliff
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.wrap().query_all_balances(&owner).unwrap(), vec![]);
+
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(100, "atom")
+    );
+}
+
+fn pending_withdrawals(app: &App, contract_addr: &Addr) -> Vec<PendingWithdrawalResp> {
+    let resp: PendingWithdrawalsResp = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &QueryMsg::PendingWithdrawals {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    resp.withdrawals
+}
+
+#[test]
+fn timelocked_withdrawals() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let guardian = Addr::unchecked("guardian");
+    let receiver = Addr::unchecked("receiver");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetTimelock {
+            timelock: Some(Timelock {
+                thresholds: coins(50, "atom"),
+                delay: 100,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::GrantRole {
+            role: Role::Guardian,
+            address: guardian.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.wrap().query_all_balances(&owner).unwrap(), vec![]);
+
+    let eta = app.block_info().time.plus_seconds(100);
+    assert_eq!(
+        pending_withdrawals(&app, &contract_addr),
+        vec![PendingWithdrawalResp {
+            withdrawal_id: 1,
+            receiver: owner.clone(),
+            funds: coins(100, "atom"),
+            cw20_funds: vec![],
+            eta,
+        }]
+    );
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::ExecuteWithdrawal { withdrawal_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::WithdrawalLocked { eta },
+        err.downcast().unwrap()
+    );
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::CancelWithdrawal { withdrawal_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        guardian.clone(),
+        contract_addr.clone(),
+        &ExecMsg::CancelWithdrawal { withdrawal_id: 1 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(pending_withdrawals(&app, &contract_addr), vec![]);
+
+    // withdrawals below the threshold are sent right away
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::WithdrawTo {
+            receiver: receiver.to_string(),
+            funds: coins(30, "atom"),
+            cw20_funds: vec![],
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&receiver).unwrap(),
+        coins(30, "atom")
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+
+    let err = app
+        .execute_contract(
+            guardian,
+            contract_addr.clone(),
+            &ExecMsg::CancelWithdrawal { withdrawal_id: 2 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::WithdrawalUnlocked { withdrawal_id: 2 },
+        err.downcast().unwrap()
+    );
+
+    // anyone can execute the withdrawal after the delay
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::ExecuteWithdrawal { withdrawal_id: 2 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(70, "atom")
+    );
+    assert_eq!(pending_withdrawals(&app, &contract_addr), vec![]);
+}
+
+// sets the timelock holding anything above 50atom for 100 seconds, with a guardian to cancel
+fn setup_timelock(app: &mut App, owner: &Addr, guardian: &Addr, contract_addr: &Addr) {
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetTimelock {
+            timelock: Some(Timelock {
+                thresholds: coins(50, "atom"),
+                delay: 100,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::GrantRole {
+            role: Role::Guardian,
+            address: guardian.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+}
+
+fn pending_changes(app: &App, contract_addr: &Addr) -> Vec<PendingChangeResp> {
+    let resp: PendingChangesResp = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &QueryMsg::PendingChanges {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    resp.changes
+}
+
+#[test]
+fn timelocked_split_withdrawals() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let guardian = Addr::unchecked("guardian");
+    let receiver = Addr::unchecked("receiver");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    setup_timelock(&mut app, &owner, &guardian, &contract_addr);
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    let withdraw = |app: &mut App| {
+        app.execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::WithdrawTo {
+                receiver: receiver.to_string(),
+                funds: coins(30, "atom"),
+                cw20_funds: vec![],
+            },
+            &[],
+        )
+        .unwrap();
+    };
+
+    // every withdrawal is below the threshold, but the second one gets the total above it
+    withdraw(&mut app);
+    withdraw(&mut app);
+    assert_eq!(
+        app.wrap().query_all_balances(&receiver).unwrap(),
+        coins(30, "atom")
+    );
+    let eta = app.block_info().time.plus_seconds(100);
+    assert_eq!(
+        pending_withdrawals(&app, &contract_addr),
+        vec![PendingWithdrawalResp {
+            withdrawal_id: 1,
+            receiver: receiver.clone(),
+            funds: coins(30, "atom"),
+            cw20_funds: vec![],
+            eta,
+        }]
+    );
+
+    // the withdrawals older than the delay don't count anymore
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    withdraw(&mut app);
+    assert_eq!(
+        app.wrap().query_all_balances(&receiver).unwrap(),
+        coins(60, "atom")
+    );
+    assert_eq!(pending_withdrawals(&app, &contract_addr).len(), 1);
+}
+
+#[test]
+fn timelocked_guardian_removal() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let guardian = Addr::unchecked("guardian");
+    let attacker = Addr::unchecked("attacker");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    setup_timelock(&mut app, &owner, &guardian, &contract_addr);
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    // a compromised owner key queues a withdrawal and tries to get rid of the guardian
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::WithdrawTo {
+            receiver: attacker.to_string(),
+            funds: coins(100, "atom"),
+            cw20_funds: vec![],
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RevokeRole {
+            role: Role::Guardian,
+            address: guardian.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ProposeOwner {
+            new_owner: attacker.to_string(),
+            expiry: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let eta = app.block_info().time.plus_seconds(100);
+    assert_eq!(
+        pending_changes(&app, &contract_addr),
+        vec![
+            PendingChangeResp {
+                change_id: 1,
+                change: ConfigChange::RevokeRole {
+                    role: Role::Guardian,
+                    address: guardian.clone(),
+                },
+                eta,
+            },
+            PendingChangeResp {
+                change_id: 2,
+                change: ConfigChange::Owner {
+                    new_owner: attacker.clone(),
+                    expiry: None,
+                },
+                eta,
+            },
+        ]
+    );
+
+    // the proposal is not made before the delay, so there is nothing to accept
+    let err = app
+        .execute_contract(
+            attacker.clone(),
+            contract_addr.clone(),
+            &ExecMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::NoPendingOwner {}, err.downcast().unwrap());
+
+    // the guardian is still there to cancel all of it
+    for msg in [
+        ExecMsg::CancelWithdrawal { withdrawal_id: 1 },
+        ExecMsg::CancelChange { change_id: 1 },
+        ExecMsg::CancelChange { change_id: 2 },
+    ] {
+        app.execute_contract(guardian.clone(), contract_addr.clone(), &msg, &[])
+            .unwrap();
+    }
+    assert_eq!(pending_withdrawals(&app, &contract_addr), vec![]);
+    assert_eq!(pending_changes(&app, &contract_addr), vec![]);
+    assert_eq!(app.wrap().query_all_balances(&attacker).unwrap(), vec![]);
+
+    // a revocation the guardian doesn't cancel takes effect after the delay
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RevokeRole {
+            role: Role::Guardian,
+            address: guardian.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    app.execute_contract(
+        owner,
+        contract_addr.clone(),
+        &ExecMsg::ExecuteChange { change_id: 3 },
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            guardian,
+            contract_addr,
+            &ExecMsg::CancelChange { change_id: 3 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: "owner".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn timelock_changes() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let guardian = Addr::unchecked("guardian");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    setup_timelock(&mut app, &owner, &guardian, &contract_addr);
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    let timelock = |app: &App| -> Option<Timelock> {
+        app.wrap()
+            .query_wasm_smart(&contract_addr, &QueryMsg::Timelock {})
+            .unwrap()
+    };
+    let set_timelock = |app: &mut App, timelock: Option<Timelock>| {
+        app.execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::SetTimelock { timelock },
+            &[],
+        )
+        .unwrap();
+    };
+
+    // removing the timelock waits for its delay, so withdrawals are still queued meanwhile
+    set_timelock(&mut app, None);
+    let eta = app.block_info().time.plus_seconds(100);
+    assert_eq!(
+        pending_changes(&app, &contract_addr),
+        vec![PendingChangeResp {
+            change_id: 1,
+            change: ConfigChange::Timelock { timelock: None },
+            eta,
+        }]
+    );
+    assert!(timelock(&app).is_some());
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.wrap().query_all_balances(&owner).unwrap(), vec![]);
+    assert_eq!(pending_withdrawals(&app, &contract_addr).len(), 1);
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::ExecuteChange { change_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::ChangeLocked { eta }, err.downcast().unwrap());
+
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::CancelChange { change_id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        guardian.clone(),
+        contract_addr.clone(),
+        &ExecMsg::CancelChange { change_id: 1 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(pending_changes(&app, &contract_addr), vec![]);
+
+    // a stricter timelock takes effect at once
+    let stricter = Timelock {
+        thresholds: coins(40, "atom"),
+        delay: 200,
+    };
+    set_timelock(&mut app, Some(stricter.clone()));
+    assert_eq!(timelock(&app), Some(stricter));
+    assert_eq!(pending_changes(&app, &contract_addr), vec![]);
+
+    // raising a threshold is weakening it, so it waits for the current delay
+    let weaker = Timelock {
+        thresholds: coins(60, "atom"),
+        delay: 200,
+    };
+    set_timelock(&mut app, Some(weaker.clone()));
+    assert_eq!(pending_changes(&app, &contract_addr).len(), 1);
+
+    app.update_block(|block| block.time = block.time.plus_seconds(200));
+    let err = app
+        .execute_contract(
+            guardian,
+            contract_addr.clone(),
+            &ExecMsg::CancelChange { change_id: 2 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::ChangeUnlocked { change_id: 2 },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::ExecuteChange { change_id: 2 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(timelock(&app), Some(weaker));
+    assert_eq!(pending_changes(&app, &contract_addr), vec![]);
+}
+
+#[test]
+fn timelocked_payees() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let guardian = Addr::unchecked("guardian");
+    let attacker = Addr::unchecked("attacker");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    setup_timelock(&mut app, &owner, &guardian, &contract_addr);
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    // the new payees wait for the delay, so they can't distribute right away
+    let payees = vec![Payee {
+        address: attacker.to_string(),
+        share: 10000,
+    }];
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetPayees {
+            payees: payees.clone(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        pending_changes(&app, &contract_addr),
+        vec![PendingChangeResp {
+            change_id: 1,
+            change: ConfigChange::Payees { payees },
+            eta: app.block_info().time.plus_seconds(100),
+        }]
+    );
+
+    let err = app
+        .execute_contract(
+            attacker.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Distribute {},
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(ContractError::NotPayee {}, err.downcast().unwrap());
+
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    app.execute_contract(
+        attacker.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ExecuteChange { change_id: 1 },
+        &[],
+    )
+    .unwrap();
+
+    // even then the distributed funds above the threshold are queued like a withdrawal
+    app.execute_contract(
+        attacker.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Distribute {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.wrap().query_all_balances(&attacker).unwrap(), vec![]);
+    assert_eq!(
+        pending_withdrawals(&app, &contract_addr),
+        vec![PendingWithdrawalResp {
+            withdrawal_id: 1,
+            receiver: attacker.clone(),
+            funds: coins(100, "atom"),
+            cw20_funds: vec![],
+            eta: app.block_info().time.plus_seconds(100),
+        }]
+    );
+
+    // the queued payout is not recorded as paid until it is sent
+    let paid = |app: &App| -> Vec<Coin> {
+        let resp: PayoutsResp = app
+            .wrap()
+            .query_wasm_smart(
+                &contract_addr,
+                &QueryMsg::Payouts {
+                    address: attacker.to_string(),
+                },
+            )
+            .unwrap();
+        resp.paid
+    };
+    assert_eq!(paid(&app), vec![]);
+
+    app.execute_contract(
+        guardian,
+        contract_addr.clone(),
+        &ExecMsg::CancelWithdrawal { withdrawal_id: 1 },
+        &[],
+    )
+    .unwrap();
+    let resp: WithdrawableResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Withdrawable {})
+        .unwrap();
+    assert_eq!(resp.funds, coins(100, "atom"));
+    assert_eq!(paid(&app), vec![]);
+
+    // distributed again, the payout is recorded once it is executed
+    app.execute_contract(
+        attacker.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Distribute {},
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    app.execute_contract(
+        attacker.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ExecuteWithdrawal { withdrawal_id: 2 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(paid(&app), coins(100, "atom"));
+}
+
+#[test]
+fn timelocked_campaign_claim() {
+    let owner = Addr::unchecked("owner");
+    let donor = Addr::unchecked("donor");
+    let guardian = Addr::unchecked("guardian");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &donor, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    setup_timelock(&mut app, &owner, &guardian, &contract_addr);
+    open_campaign(&mut app, &owner, &contract_addr);
+    app.execute_contract(
+        donor,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+    app.update_block(|block| block.time = block.time.plus_seconds(1000));
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ClaimCampaignFunds { campaign_id: 1 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.wrap().query_all_balances(&owner).unwrap(), vec![]);
+    assert_eq!(
+        pending_withdrawals(&app, &contract_addr),
+        vec![PendingWithdrawalResp {
+            withdrawal_id: 1,
+            receiver: owner.clone(),
+            funds: coins(100, "atom"),
+            cw20_funds: vec![],
+            eta: app.block_info().time.plus_seconds(100),
+        }]
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(100));
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ExecuteWithdrawal { withdrawal_id: 1 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(100, "atom")
+    );
+}
+
+#[test]
+fn withdrawal_reserves() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let receiver = Addr::unchecked("receiver");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            sender,
+            contract_addr.clone(),
+            &ExecMsg::SetReserves { reserves: vec![] },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetReserves {
+            reserves: coins(30, "atom"),
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: ReservesResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Reserves {})
+        .unwrap();
+    assert_eq!(resp.reserves, coins(30, "atom"));
+
+    let resp: WithdrawableResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Withdrawable {})
+        .unwrap();
+    assert_eq!(
+        resp,
+        WithdrawableResp {
+            funds: coins(70, "atom"),
+            cw20_funds: vec![],
+        }
+    );
+
+    // the limit above the available balance doesn't reach into the reserve
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::WithdrawTo {
+            receiver: receiver.to_string(),
+            funds: coins(100, "atom"),
+            cw20_funds: vec![],
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&receiver).unwrap(),
+        coins(70, "atom")
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(app.wrap().query_all_balances(&owner).unwrap(), vec![]);
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        coins(30, "atom")
+    );
+
+    // dropping the reserve releases it
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetReserves { reserves: vec![] },
+        &[],
+    )
+    .unwrap();
+
+    let resp: WithdrawableResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Withdrawable {})
+        .unwrap();
+    assert_eq!(resp.funds, coins(30, "atom"));
+}
+
+fn withdrawal_log(
+    app: &App,
+    contract_addr: &Addr,
+    receiver: Option<&Addr>,
+    from: Option<Timestamp>,
+    to: Option<Timestamp>,
+) -> Vec<(u64, Addr, Vec<Coin>)> {
+    let resp: WithdrawalLogResp = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &QueryMsg::WithdrawalLog {
+                receiver: receiver.map(Addr::to_string),
+                from,
+                to,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    resp.entries
+        .into_iter()
+        .map(|entry| (entry.log_id, entry.receiver, entry.funds))
+        .collect()
+}
+
+#[test]
+fn withdrawal_history() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    let start = app.block_info().time;
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(100, "atom"),
+    )
+    .unwrap();
+
+    for (receiver, amount) in [(&alice, 20), (&bob, 30)] {
+        app.execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::WithdrawTo {
+                receiver: receiver.to_string(),
+                funds: coins(amount, "atom"),
+                cw20_funds: vec![],
+            },
+            &[],
+        )
+        .unwrap();
+        app.update_block(|block| block.time = block.time.plus_seconds(100));
+    }
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    // withdrawing nothing is not logged
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        withdrawal_log(&app, &contract_addr, None, None, None),
+        vec![
+            (1, alice.clone(), coins(20, "atom")),
+            (2, bob.clone(), coins(30, "atom")),
+            (3, owner.clone(), coins(50, "atom")),
+        ]
+    );
+    assert_eq!(
+        withdrawal_log(&app, &contract_addr, Some(&bob), None, None),
+        vec![(2, bob.clone(), coins(30, "atom"))]
+    );
+    assert_eq!(
+        withdrawal_log(
+            &app,
+            &contract_addr,
+            None,
+            Some(start.plus_seconds(100)),
+            Some(start.plus_seconds(200)),
+        ),
+        vec![(2, bob, coins(30, "atom"))]
+    );
+
+    let resp: WithdrawalLogResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::WithdrawalLog {
+                receiver: None,
+                from: None,
+                to: None,
+                start_after: Some(2),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.entries,
+        vec![WithdrawalLogEntryResp {
+            log_id: 3,
+            executor: owner.clone(),
+            receiver: owner,
+            funds: coins(50, "atom"),
+            cw20_funds: vec![],
+            height: app.block_info().height,
+            time: start.plus_seconds(200),
+        }]
+    );
+}
+
+fn staking_status(app: &App, contract_addr: &Addr) -> StakingStatusResp {
+    app.wrap()
+        .query_wasm_smart(contract_addr, &QueryMsg::StakingStatus {})
+        .unwrap()
+}
+
+#[test]
+fn staking() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let validator = "validator";
+    let year = 60 * 60 * 24 * 365;
+
+    let mut app = App::new(|router, api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(1000, "atom"))
+            .unwrap();
+        router
+            .staking
+            .setup(
+                storage,
+                StakingInfo {
+                    bonded_denom: "atom".to_owned(),
+                    unbonding_time: 60,
+                    apr: Decimal::percent(10),
+                },
+            )
+            .unwrap();
+        router
+            .staking
+            .add_validator(
+                api,
+                storage,
+                &mock_env().block,
+                Validator {
+                    address: validator.to_owned(),
+                    commission: Decimal::zero(),
+                    max_commission: Decimal::one(),
+                    max_change_rate: Decimal::one(),
+                },
+            )
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    app.execute_contract(
+        sender,
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(1000, "atom"),
+    )
+    .unwrap();
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Stake {
+                amount: Uint128::new(600),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::StakingNotConfigured {},
+        err.downcast().unwrap()
+    );
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::SetStaking {
+                staking: Some(StakingConfig {
+                    validator: "unknown".to_owned(),
+                    unbonding_period: 60,
+                }),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::ValidatorNotFound {
+            validator: "unknown".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetStaking {
+            staking: Some(StakingConfig {
+                validator: validator.to_owned(),
+                unbonding_period: 60,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+
+    // the reserve is never staked
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetReserves {
+            reserves: coins(100, "atom"),
+        },
+        &[],
+    )
+    .unwrap();
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Stake {
+                amount: Uint128::new(901),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::InsufficientFunds {
+            available: coin(900, "atom")
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Stake {
+            amount: Uint128::new(600),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        coins(400, "atom")
+    );
+    assert_eq!(
+        staking_status(&app, &contract_addr).bonded,
+        coins(600, "atom")
+    );
+
+    app.update_block(|block| block.time = block.time.plus_seconds(year));
+    assert_eq!(
+        staking_status(&app, &contract_addr).rewards,
+        coins(60, "atom")
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::ClaimRewards { compound: true },
+        &[],
+    )
+    .unwrap();
+    let status = staking_status(&app, &contract_addr);
+    assert_eq!(status.bonded, coins(660, "atom"));
+    assert_eq!(status.rewards, vec![]);
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::SetStaking { staking: None },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::StillDelegated {
+            validator: validator.to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Unstake { amount: None },
+        &[],
+    )
+    .unwrap();
+    let completion = app.block_info().time.plus_seconds(60);
+    let status = staking_status(&app, &contract_addr);
+    assert_eq!(status.bonded, vec![]);
+    assert_eq!(
+        status.unbonding,
+        vec![Unbonding {
+            amount: coin(660, "atom"),
+            completion,
+        }]
+    );
+
+    // only the liquid funds are withdrawn while the rest is unbonding
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(300, "atom")
+    );
+
+    // the unbonding queue is processed with every new block
+    app.update_block(|block| block.time = block.time.plus_seconds(60));
+    app.update_block(|block| block.height += 1);
+    assert_eq!(staking_status(&app, &contract_addr).unbonding, vec![]);
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(960, "atom")
+    );
+    assert_eq!(
+        app.wrap().query_all_balances(&contract_addr).unwrap(),
+        coins(100, "atom")
+    );
+}
+
+// a cw20-base token which can be frozen with a sudo message, so any transfer out of it fails
+const FROZEN: Item<bool> = Item::new("frozen");
+
+fn freezable_cw20_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        |deps: DepsMut, env: Env, info: MessageInfo, msg: Cw20ExecuteMsg| {
+            if FROZEN.may_load(deps.storage)?.unwrap_or_default() {
+                return Err(cw20_base::ContractError::Std(StdError::generic_err(
+                    "token is frozen",
+                )));
+            }
+            cw20_base::contract::execute(deps, env, info, msg)
+        },
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    )
+    .with_sudo(
+        |deps: DepsMut, _env: Env, frozen: bool| -> StdResult<Response> {
+            FROZEN.save(deps.storage, &frozen)?;
+            Ok(Response::new())
+        },
+    );
+    Box::new(contract)
+}
+
+fn failed_payouts(app: &App, contract_addr: &Addr) -> Vec<FailedPayoutResp> {
+    let resp: FailedPayoutsResp = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &QueryMsg::FailedPayouts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    resp.payouts
+}
+
+#[test]
+fn failed_payouts_retry() {
+    let owner = Addr::unchecked("owner");
+    let donor = Addr::unchecked("donor");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &donor, coins(50, "atom"))
+            .unwrap();
+    });
+    let token_id = app.store_code(freezable_cw20_contract());
+    let token = app
+        .instantiate_contract(
+            token_id,
+            owner.clone(),
+            &cw20_base::msg::InstantiateMsg {
+                name: "Frozen token".to_owned(),
+                symbol: "FRZ".to_owned(),
+                decimals: 6,
+                initial_balances: vec![Cw20Coin {
+                    address: donor.to_string(),
+                    amount: Uint128::new(100),
+                }],
+                mint: None,
+                marketing: None,
+            },
+            &[],
+            "Cw20 token",
+            None,
+        )
+        .unwrap();
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            minimal_donations: coins(10, "atom"),
+            accepted_cw20s: vec![Cw20Coin {
+                address: token.to_string(),
+                amount: Uint128::new(10),
+            }],
+            ..Default::default()
+        },
+    );
+
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(50, "atom"),
+    )
+    .unwrap();
+    donate_cw20(&mut app, &donor, &token, &contract_addr, 100, None).unwrap();
+
+    // the failing token payout doesn't stop the native one
+    app.wasm_sudo(token.clone(), &true).unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Withdraw {},
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        app.wrap().query_all_balances(&owner).unwrap(),
+        coins(50, "atom")
+    );
+
+    let payouts = failed_payouts(&app, &contract_addr);
+    assert_eq!(payouts.len(), 1);
+    assert!(payouts[0].error.contains("token is frozen"));
+    assert_eq!(
+        payouts[0],
+        FailedPayoutResp {
+            payout_id: 2,
+            log_id: 1,
+            receiver: owner.clone(),
+            funds: vec![],
+            cw20_funds: vec![Cw20Coin {
+                address: token.to_string(),
+                amount: Uint128::new(100),
+            }],
+            error: payouts[0].error.clone(),
+        }
+    );
+
+    // the tokens are held for the failed payout, so they can't be withdrawn again
+    let resp: WithdrawableResp = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::Withdrawable {})
+        .unwrap();
+    assert_eq!(resp.cw20_funds, vec![]);
+
+    // retrying while the token is still frozen fails the payout again
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RetryPayout { payout_id: 2 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(failed_payouts(&app, &contract_addr).len(), 1);
+
+    app.wasm_sudo(token.clone(), &false).unwrap();
+    app.execute_contract(
+        donor.clone(),
+        contract_addr.clone(),
+        &ExecMsg::RetryPayout { payout_id: 2 },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(cw20_balance(&app, &token, &owner), 100);
+    assert_eq!(failed_payouts(&app, &contract_addr), vec![]);
+
+    let err = app
+        .execute_contract(
+            donor,
+            contract_addr,
+            &ExecMsg::RetryPayout { payout_id: 2 },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::PayoutNotFound { payout_id: 2 },
+        err.downcast().unwrap()
+    );
+}
+
+fn counter_value(app: &App, contract_addr: &Addr, name: Option<&str>) -> u128 {
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(
+            contract_addr,
+            &QueryMsg::Value {
+                name: name.map(str::to_owned),
+            },
+        )
+        .unwrap();
+    resp.value.u128()
+}
+
+#[test]
+fn named_counters() {
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, vec![coin(20, "atom"), coin(300, "osmo")])
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+
+    let create_counter = ExecMsg::CreateCounter {
+        name: "osmo".to_owned(),
+        minimal_donations: coins(100, "osmo"),
+        counting_policy: CountingPolicy::Proportional,
+        owner: Some(alice.to_string()),
+    };
+    let err = app
+        .execute_contract(alice.clone(), contract_addr.clone(), &create_counter, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(owner.clone(), contract_addr.clone(), &create_counter, &[])
+        .unwrap();
+    let err = app
+        .execute_contract(owner.clone(), contract_addr.clone(), &create_counter, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::CounterExists {
+            name: "osmo".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    // the named counter counts by its own minimal donations and policy
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: Some("osmo".to_owned()),
+        },
+        &[coin(10, "atom"), coin(300, "osmo")],
+    )
+    .unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, Some("osmo")), 3);
+    assert_eq!(counter_value(&app, &contract_addr, None), 0);
+
+    app.execute_contract(
+        sender.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+        &coins(10, "atom"),
+    )
+    .unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, Some("osmo")), 3);
+    assert_eq!(counter_value(&app, &contract_addr, None), 1);
+
+    let err = app
+        .execute_contract(
+            sender,
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: Some("unknown".to_owned()),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::CounterNotFound {
+            name: "unknown".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+
+    // only the counter owner can reset it, even the contract owner can't
+    let reset = ExecMsg::Reset {
+        counter: Uint128::new(10),
+        name: Some("osmo".to_owned()),
+    };
+    let err = app
+        .execute_contract(owner.clone(), contract_addr.clone(), &reset, &[])
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: alice.to_string()
+        },
+        err.downcast().unwrap()
+    );
+    app.execute_contract(alice.clone(), contract_addr.clone(), &reset, &[])
+        .unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, Some("osmo")), 10);
+    assert_eq!(counter_value(&app, &contract_addr, None), 1);
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::CreateCounter {
+            name: "atom".to_owned(),
+            minimal_donations: coins(5, "atom"),
+            counting_policy: CountingPolicy::OncePerMessage,
+            owner: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let resp: CountersResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Counters {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.counters,
+        vec![
+            CounterResp {
+                name: "atom".to_owned(),
+                value: Uint128::new(0),
+                owner: owner.clone(),
+                minimal_donations: coins(5, "atom"),
+                counting_policy: CountingPolicy::OncePerMessage,
+            },
+            CounterResp {
+                name: "osmo".to_owned(),
+                value: Uint128::new(10),
+                owner: alice,
+                minimal_donations: coins(100, "osmo"),
+                counting_policy: CountingPolicy::Proportional,
+            },
+        ]
+    );
+
+    let resp: CountersResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::Counters {
+                start_after: Some("atom".to_owned()),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.counters.len(), 1);
+    assert_eq!(resp.counters[0].name, "osmo");
+}
+
+fn value_at(app: &App, contract_addr: &Addr, height: u64) -> AnyResult<u128> {
+    let resp: ValueResp = app
+        .wrap()
+        .query_wasm_smart(contract_addr, &QueryMsg::ValueAt { height })?;
+    Ok(resp.value.u128())
+}
+
+#[test]
+fn counter_history() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(25, "atom"))
+            .unwrap();
+    });
+    let contract_addr = instantiate_counting(&mut app, &owner);
+    let start = app.block_info();
+
+    // the non-qualifying donation doesn't change the counter, so it's not in the history
+    for amount in [10, 5, 10] {
+        app.execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &coins(amount, "atom"),
+        )
+        .unwrap();
+        app.update_block(|block| {
+            block.height += 1;
+            block.time = block.time.plus_seconds(5);
+        });
+    }
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Reset {
+            counter: Uint128::new(10),
+            name: None,
+        },
+        &[],
+    )
+    .unwrap();
+    app.update_block(|block| block.height += 1);
+
+    assert!(value_at(&app, &contract_addr, start.height - 1).is_err());
+    assert!(value_at(&app, &contract_addr, u64::MAX).is_err());
+    assert_eq!(value_at(&app, &contract_addr, start.height).unwrap(), 1);
+    assert_eq!(value_at(&app, &contract_addr, start.height + 1).unwrap(), 1);
+    assert_eq!(value_at(&app, &contract_addr, start.height + 2).unwrap(), 2);
+    assert_eq!(
+        value_at(&app, &contract_addr, start.height + 3).unwrap(),
+        10
+    );
+    assert_eq!(
+        value_at(&app, &contract_addr, start.height + 4).unwrap(),
+        10
+    );
+
+    let resp: ValueHistoryResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::ValueHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    let change = |change_id, value: u128, blocks: u64, cause| ValueChangeResp {
+        change_id,
+        value: Uint128::new(value),
+        height: start.height + blocks,
+        time: start.time.plus_seconds(5 * blocks),
+        cause,
+    };
+    assert_eq!(
+        resp.changes,
+        vec![
+            change(1, 0, 0, CounterChangeCause::Instantiate),
+            change(2, 1, 0, CounterChangeCause::Donate),
+            change(3, 2, 2, CounterChangeCause::Donate),
+            change(4, 10, 3, CounterChangeCause::Reset),
+        ]
+    );
+
+    let resp: ValueHistoryResp = app
+        .wrap()
+        .query_wasm_smart(
+            &contract_addr,
+            &QueryMsg::ValueHistory {
+                start_after: Some(2),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        resp.changes,
+        vec![change(3, 2, 2, CounterChangeCause::Donate)]
+    );
+}
+
+#[test]
+fn counter_limit() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::new(|router, _api, storage| {
+        router
+            .bank
+            .init_balance(storage, &sender, coins(100, "atom"))
+            .unwrap();
+    });
+    // the counter starts at the largest value the old u64 counter could hold
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::from(u64::MAX),
+            minimal_donations: coins(1, "atom"),
+            counting_policy: CountingPolicy::Proportional,
+            ..Default::default()
+        },
+    );
+    let donate = |app: &mut App, amount: u128| {
+        app.execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Donate {
+                memo: None,
+                name: None,
+            },
+            &coins(amount, "atom"),
+        )
+    };
+
+    // without a limit the counter just goes past the u64 range
+    donate(&mut app, 10).unwrap();
+    assert_eq!(
+        counter_value(&app, &contract_addr, None),
+        u64::MAX as u128 + 10
+    );
+
+    let limit = CounterLimit {
+        max: Uint128::from(u64::MAX as u128 + 15),
+        on_reached: LimitMode::Saturate,
+    };
+    let err = app
+        .execute_contract(
+            sender.clone(),
+            contract_addr.clone(),
+            &ExecMsg::SetCounterLimit {
+                limit: Some(limit.clone()),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::Unauthorized {
+            owner: owner.to_string()
+        },
+        err.downcast().unwrap()
+    );
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetCounterLimit {
+            limit: Some(limit.clone()),
+        },
+        &[],
+    )
+    .unwrap();
+    let resp: Option<CounterLimit> = app
+        .wrap()
+        .query_wasm_smart(&contract_addr, &QueryMsg::CounterLimit {})
+        .unwrap();
+    assert_eq!(resp, Some(limit.clone()));
+
+    // saturating stops the counter at the maximum
+    donate(&mut app, 10).unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, None), limit.max.u128());
+
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetCounterLimit {
+            limit: Some(CounterLimit {
+                max: Uint128::new(20),
+                on_reached: LimitMode::Reject,
+            }),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::Reset {
+            counter: Uint128::new(15),
+            name: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    // rejecting fails the donation and leaves the counter alone
+    let err = donate(&mut app, 10).unwrap_err();
+    assert_eq!(
+        ContractError::CounterLimitReached {
+            max: Uint128::new(20)
+        },
+        err.downcast().unwrap()
+    );
+    assert_eq!(counter_value(&app, &contract_addr, None), 15);
+    assert_eq!(
+        app.wrap().query_balance(&sender, "atom").unwrap(),
+        coin(80, "atom")
+    );
+
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            contract_addr.clone(),
+            &ExecMsg::Reset {
+                counter: Uint128::new(21),
+                name: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        ContractError::CounterLimitReached {
+            max: Uint128::new(20)
+        },
+        err.downcast().unwrap()
+    );
+
+    donate(&mut app, 5).unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, None), 20);
+
+    // removing the limit lets the counter grow again
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::SetCounterLimit { limit: None },
+        &[],
+    )
+    .unwrap();
+    donate(&mut app, 5).unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, None), 25);
+
+    // even the wide counter has its end, going past it fails instead of wrapping around
+    app.execute_contract(
+        owner,
+        contract_addr.clone(),
+        &ExecMsg::Reset {
+            counter: Uint128::MAX,
+            name: None,
+        },
+        &[],
+    )
+    .unwrap();
+    let err = donate(&mut app, 5).unwrap_err();
+    assert!(matches!(
+        err.downcast().unwrap(),
+        ContractError::Overflow(_)
+    ));
+    assert_eq!(counter_value(&app, &contract_addr, None), u128::MAX);
+}
+
+#[test]
+fn migrate_u64_counters() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+
+    // the state as it was left by the code storing the counters as u64
+    // with the single minimal donation of the first version
+    legacy::MINIMAL_DONATION
+        .save(&mut deps.storage, &coin(10, "atom"))
+        .unwrap();
+    legacy::COUNTER
+        .save(&mut deps.storage, &Stored::Old(7))
+        .unwrap();
+    LAST_COUNTER_CHANGE_ID.save(&mut deps.storage, &1).unwrap();
+    // the instantiation leaves a change without the old value behind
+    legacy::COUNTER_CHANGELOG
+        .save(&mut deps.storage, 5, &Stored::Old(ChangeSet { old: None }))
+        .unwrap();
+    legacy::COUNTER_CHANGELOG
+        .save(
+            &mut deps.storage,
+            10,
+            &Stored::Old(ChangeSet { old: Some(3) }),
+        )
+        .unwrap();
+    legacy::COUNTER_HISTORY
+        .save(
+            &mut deps.storage,
+            1,
+            &Stored::Old(legacy::CounterChange {
+                value: 7,
+                height: 10,
+                time: env.block.time,
+                cause: CounterChangeCause::Donate,
+            }),
+        )
+        .unwrap();
+    legacy::COUNTERS
+        .save(
+            &mut deps.storage,
+            "atom",
+            &Stored::Old(legacy::CounterState {
+                value: 4,
+                owner: Addr::unchecked("owner"),
+                minimal_donations: coins(5, "atom"),
+                counting_policy: CountingPolicy::OncePerMessage,
+            }),
+        )
+        .unwrap();
+
+    let resp = migrate(deps.as_mut(), env.clone(), MigrateMsg { limit: None }).unwrap();
+    assert_eq!(resp.attributes[1], attr("migrated", "5"));
+    assert_eq!(resp.attributes[2], attr("finished", "true"));
+
+    assert_eq!(COUNTER.load(&deps.storage).unwrap(), Uint128::new(7));
+    assert_eq!(COUNTER.may_load_at_height(&deps.storage, 5).unwrap(), None);
+    assert_eq!(
+        COUNTER.may_load_at_height(&deps.storage, 10).unwrap(),
+        Some(Uint128::new(3))
+    );
+    assert_eq!(
+        COUNTER_HISTORY.load(&deps.storage, 1).unwrap().value,
+        Uint128::new(7)
+    );
+    assert_eq!(
+        COUNTERS.load(&deps.storage, "atom").unwrap().value,
+        Uint128::new(4)
+    );
+
+    let resp: ValueResp = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Value {
+                name: Some("atom".to_owned()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(resp.value, Uint128::new(4));
+
+    // running the migration again finds nothing left to migrate
+    let resp = migrate(deps.as_mut(), env.clone(), MigrateMsg { limit: None }).unwrap();
+    assert_eq!(resp.attributes[1], attr("migrated", "0"));
+    assert_eq!(COUNTER.load(&deps.storage).unwrap(), Uint128::new(7));
+
+    // the old minimal donation still makes the donations count
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("sender", &coins(10, "atom")),
+        ExecMsg::Donate {
+            memo: None,
+            name: None,
+        },
+    )
+    .unwrap();
+    let resp: ValueResp =
+        from_json(query(deps.as_ref(), env, QueryMsg::Value { name: None }).unwrap()).unwrap();
+    assert_eq!(resp.value, Uint128::new(8));
+}
+
+#[test]
+fn named_cw20_counter() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+
+    let mut app = App::default();
+    let token = instantiate_cw20(
+        &mut app,
+        &owner,
+        "TKN",
+        vec![Cw20Coin {
+            address: sender.to_string(),
+            amount: Uint128::new(150),
+        }],
+    );
+    let contract_addr = instantiate_with(
+        &mut app,
+        &owner,
+        InstantiateMsg {
+            counter: Uint128::zero(),
+            minimal_donations: coins(10, "atom"),
+            accepted_cw20s: vec![Cw20Coin {
+                address: token.to_string(),
+                amount: Uint128::new(10),
+            }],
+            ..Default::default()
+        },
+    );
+    app.execute_contract(
+        owner.clone(),
+        contract_addr.clone(),
+        &ExecMsg::CreateCounter {
+            name: "tkn".to_owned(),
+            minimal_donations: coins(25, token.as_str()),
+            counting_policy: CountingPolicy::Proportional,
+            owner: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let donate = |app: &mut App, name: Option<&str>| {
+        app.execute_contract(
+            sender.clone(),
+            token.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: contract_addr.to_string(),
+                amount: Uint128::new(50),
+                msg: to_json_binary(&ReceiveMsg::Donate {
+                    memo: None,
+                    name: name.map(str::to_owned),
+                })
+                .unwrap(),
+            },
+            &[],
+        )
+    };
+
+    // the named counter counts the tokens by its own minimal donation
+    donate(&mut app, Some("tkn")).unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, Some("tkn")), 2);
+    assert_eq!(counter_value(&app, &contract_addr, None), 0);
+
+    donate(&mut app, None).unwrap();
+    assert_eq!(counter_value(&app, &contract_addr, Some("tkn")), 2);
+    assert_eq!(counter_value(&app, &contract_addr, None), 1);
+
+    let err = donate(&mut app, Some("missing")).unwrap_err();
+    assert_eq!(
+        ContractError::CounterNotFound {
+            name: "missing".to_owned()
+        },
+        err.downcast().unwrap()
+    );
+}
+
+#[test]
+fn migrate_in_batches() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+
+    for (name, value) in [("atom", 1), ("btc", 2), ("eth", 3)] {
+        legacy::COUNTERS
+            .save(
+                &mut deps.storage,
+                name,
+                &Stored::Old(legacy::CounterState {
+                    value,
+                    owner: Addr::unchecked("owner"),
+                    minimal_donations: coins(5, "atom"),
+                    counting_policy: CountingPolicy::OncePerMessage,
+                }),
+            )
+            .unwrap();
+    }
+    for height in 1..=3 {
+        legacy::COUNTER_CHANGELOG
+            .save(
+                &mut deps.storage,
+                height,
+                &Stored::Old(ChangeSet { old: Some(height) }),
+            )
+            .unwrap();
+    }
+
+    // every batch rewrites two rows and picks up where the previous one stopped
+    let msg = MigrateMsg { limit: Some(2) };
+    let resp = migrate(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+    assert_eq!(resp.attributes[1], attr("migrated", "2"));
+    assert_eq!(resp.attributes[2], attr("finished", "false"));
+    assert_eq!(
+        COUNTERS.load(&deps.storage, "btc").unwrap().value,
+        Uint128::new(2)
+    );
+    assert!(COUNTERS.load(&deps.storage, "eth").is_err());
+
+    let resp = migrate(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+    assert_eq!(resp.attributes[1], attr("migrated", "2"));
+    assert_eq!(resp.attributes[2], attr("finished", "false"));
+    assert_eq!(
+        COUNTERS.load(&deps.storage, "eth").unwrap().value,
+        Uint128::new(3)
+    );
+
+    let resp = migrate(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+    assert_eq!(resp.attributes[1], attr("migrated", "2"));
+    assert_eq!(resp.attributes[2], attr("finished", "true"));
+    assert_eq!(
+        COUNTER.may_load_at_height(&deps.storage, 3).unwrap(),
+        Some(Uint128::new(3))
+    );
+
+    // the next migration walks through everything again without changing anything
+    let resp = migrate(deps.as_mut(), env, msg).unwrap();
+    assert_eq!(resp.attributes[1], attr("migrated", "0"));
+    assert_eq!(resp.attributes[2], attr("finished", "false"));
+}
@@ -1,5 +1,15 @@
-use cosmwasm_std::{Addr, Coin};
-use cw_storage_plus::Item;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
+use cw20::Cw20Coin;
+use cw_storage_plus::{
+    Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotItem, Strategy,
+};
+use cw_utils::Expiration;
+
+use crate::msg::{
+    BadgeThresholds, BadgeTier, CampaignStatus, ConfigChange, CounterChangeCause, CounterLimit,
+    CountingPolicy, RefundMode, StakingConfig, Timelock, Unbonding, VestingSchedule,
+};
 
 /// the contract state is defined by creating accessors to the state objects
 /// instead of defining state variables we are creating atoms like Item
@@ -7,11 +17,421 @@ use cw_storage_plus::Item;
 /// Item is used to access the values on the blockchain
 /// the string passed to Item on instantiation is part of a key to address the data
 /// Item takes care of serialization and deserialization
-pub const COUNTER: Item<u64> = Item::new("counter");
-/// Coin is a type representing a single native token amount 
+/// the SnapshotItem keeps the previous value on every change, by the block height
+/// so it can tell what the counter was at any block - the current value is kept at the same key
+/// counters are Uint128, so even the proportional counting can't realistically overflow them
+pub const COUNTER: SnapshotItem<Uint128> = SnapshotItem::new(
+    "counter",
+    "counter__checkpoints",
+    "counter__changelog",
+    Strategy::EveryBlock,
+);
+/// every change of the counter with its cause, keyed by a sequential id
+pub const COUNTER_HISTORY: Map<u64, CounterChange> = Map::new("counter_history");
+pub const LAST_COUNTER_CHANGE_ID: Item<u64> = Item::new("last_counter_change_id");
+
+#[cw_serde]
+pub struct CounterChange {
+    pub value: Uint128,
+    pub height: u64,
+    pub time: Timestamp,
+    pub cause: CounterChangeCause,
+}
+
+/// the maximum of all the counters, they are not limited if it's not set
+pub const COUNTER_LIMIT: Item<CounterLimit> = Item::new("counter_limit");
+
+/// counters created next to the default one, keyed by their names
+/// the default counter keeps its configuration in the items below, so older messages still work
+pub const COUNTERS: Map<&str, CounterState> = Map::new("counters");
+
+#[cw_serde]
+pub struct CounterState {
+    pub value: Uint128,
+    // only the owner of the counter can reset it
+    pub owner: Addr,
+    pub minimal_donations: Vec<Coin>,
+    pub counting_policy: CountingPolicy,
+}
+
+impl CounterState {
+    pub fn minimal_donation(&self, denom: &str) -> Option<Uint128> {
+        self.minimal_donations
+            .iter()
+            .find(|minimal| minimal.denom == denom)
+            .map(|minimal| minimal.amount)
+    }
+}
+
+/// Coin is a type representing a single native token amount
 /// it contains a denominator (its unique identifier) and the number of tokens sent
-/// contract should only accept messages with a minimal amount of coins
-/// setting constant for this minimal amount
-pub const MINIMAL_DONATION: Item<Coin> = Item::new("minimal_donation");
+/// contract should only count messages with a minimal amount of coins
+/// every accepted denom has its own minimal amount, so the map is keyed by the denom
+pub const MINIMAL_DONATIONS: Map<&str, Uint128> = Map::new("minimal_donations");
+/// minimal donations of the accepted cw20 tokens, keyed by the token contract address
+/// in donor totals and on the leaderboard the token address is used as the denom
+pub const ACCEPTED_CW20: Map<&Addr, Uint128> = Map::new("accepted_cw20");
+/// cw20 tokens the contract ever received, so they can be withdrawn
+/// kept apart from the accepted tokens, so no longer accepting a token doesn't lock its balance
+pub const HELD_CW20: Map<&Addr, ()> = Map::new("held_cw20");
+/// how donations increment the counter
+/// contracts instantiated before it was introduced count once per message
+pub const COUNTING_POLICY: Item<CountingPolicy> = Item::new("counting_policy");
+/// which part of the donated funds is sent back to the donor
+pub const REFUND_MODE: Item<RefundMode> = Item::new("refund_mode");
+/// in the strict mode donations which don't count fail, and other messages reject funds
+pub const STRICT_MODE: Item<bool> = Item::new("strict_mode");
+/// releases the donations for withdrawal over time, everything is released if it's not set
+pub const VESTING: Item<VestingSchedule> = Item::new("vesting");
+/// everything withdrawn or distributed so far, per denom
+/// cw20 tokens are tracked with their address as the denom
+pub const WITHDRAWN: Map<&str, Uint128> = Map::new("withdrawn");
+
+/// amounts of every denom which are never withdrawn or distributed
+/// keeping them in the contract leaves funds for refunds and matching
+/// cw20 tokens use the token address as the denom
+pub const RESERVES: Map<&str, Uint128> = Map::new("reserves");
+
+/// withdrawals above the thresholds wait for the delay, so they can be cancelled
+pub const TIMELOCK: Item<Timelock> = Item::new("timelock");
+/// queued withdrawals, keyed by a sequential id
+/// the queued funds are held for the withdrawal, so they can't be withdrawn again
+pub const PENDING_WITHDRAWALS: Map<u64, PendingWithdrawal> = Map::new("pending_withdrawals");
+pub const LAST_WITHDRAWAL_ID: Item<u64> = Item::new("last_withdrawal_id");
+/// withdrawals sent without the delay, until they are older than the delay
+/// the thresholds limit their total, so splitting a withdrawal doesn't get around the timelock
+pub const RECENT_WITHDRAWALS: Item<Vec<RecentWithdrawal>> = Item::new("recent_withdrawals");
+/// queued changes of the timelock and the payees, keyed by a sequential id
+pub const PENDING_CHANGES: Map<u64, PendingChange> = Map::new("pending_changes");
+pub const LAST_CHANGE_ID: Item<u64> = Item::new("last_change_id");
+
+/// payouts sent as submessages, keyed by the payout id which is also the reply id
+/// they are only kept until the reply tells if they were delivered
+pub const SENT_PAYOUTS: Map<u64, Payout> = Map::new("sent_payouts");
+/// payouts which failed, until they are retried
+/// their funds were already withdrawn, so they are held for the receiver
+pub const FAILED_PAYOUTS: Map<u64, FailedPayout> = Map::new("failed_payouts");
+pub const LAST_PAYOUT_ID: Item<u64> = Item::new("last_payout_id");
+
+// every payout is a single message - either a bank send of the coins, or a single cw20 transfer
+#[cw_serde]
+pub struct Payout {
+    // the withdrawal the payout was sent for
+    pub log_id: u64,
+    pub receiver: Addr,
+    pub funds: Vec<Coin>,
+    pub cw20_funds: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct FailedPayout {
+    pub payout: Payout,
+    pub error: String,
+}
+
+/// the validator idle funds of the bonded denom are delegated to
+pub const STAKING: Item<StakingConfig> = Item::new("staking");
+/// undelegated funds until they are back in the balance
+/// the chain doesn't tell the contract about unbonding, so it has to keep track of it itself
+pub const UNBONDING: Item<Vec<Unbonding>> = Item::new("unbonding");
+
+#[cw_serde]
+pub struct PendingWithdrawal {
+    pub receiver: Addr,
+    pub funds: Vec<Coin>,
+    pub cw20_funds: Vec<Cw20Coin>,
+    pub eta: Timestamp,
+    // the campaign claimed by the withdrawal - its funds are not counted as withdrawn
+    #[serde(default)]
+    pub campaign_id: Option<u64>,
+    // a distributed payout is recorded for the payee once it is sent
+    #[serde(default)]
+    pub payout: bool,
+}
+
+#[cw_serde]
+pub struct RecentWithdrawal {
+    // cw20 tokens use the token address as the denom, like the thresholds
+    pub funds: Vec<Coin>,
+    pub time: Timestamp,
+}
+
+#[cw_serde]
+pub struct PendingChange {
+    pub change: ConfigChange,
+    pub eta: Timestamp,
+}
+
+/// every withdrawal sent out of the contract, keyed by a sequential id
+/// indexed by the receiver, so the payouts of a single receiver can be listed
+pub const WITHDRAWAL_LOG: IndexedMap<u64, WithdrawalLogEntry, WithdrawalLogIndexes> =
+    IndexedMap::new(
+        "withdrawal_log",
+        WithdrawalLogIndexes {
+            receiver: MultiIndex::new(
+                |_pk, entry| entry.receiver.clone(),
+                "withdrawal_log",
+                "withdrawal_log__receiver",
+            ),
+        },
+    );
+pub const LAST_LOG_ID: Item<u64> = Item::new("last_log_id");
+
+#[cw_serde]
+pub struct WithdrawalLogEntry {
+    // who sent the message paying the withdrawal out
+    pub executor: Addr,
+    pub receiver: Addr,
+    pub funds: Vec<Coin>,
+    pub cw20_funds: Vec<Cw20Coin>,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+pub struct WithdrawalLogIndexes<'a> {
+    pub receiver: MultiIndex<'a, Addr, WithdrawalLogEntry, u64>,
+}
+
+impl<'a> IndexList<WithdrawalLogEntry> for WithdrawalLogIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<WithdrawalLogEntry>> + '_> {
+        let v: Vec<&dyn Index<WithdrawalLogEntry>> = vec![&self.receiver];
+        Box::new(v.into_iter())
+    }
+}
+
+/// beneficiaries of the distributed funds, in the order they were configured
+/// the first payee gets the rounding dust, so the whole balance is always distributed
+pub const PAYEES: Item<Vec<PayeeShare>> = Item::new("payees");
+/// everything distributed to every payee so far
+pub const PAYOUTS: Map<&Addr, Vec<Coin>> = Map::new("payouts");
+
+#[cw_serde]
+pub struct PayeeShare {
+    pub address: Addr,
+    // in basis points
+    pub share: u16,
+}
+
+/// paused messages, nothing is paused if it's not set
+pub const PAUSED: Item<Paused> = Item::new("paused");
+
+#[cw_serde]
+pub struct Paused {
+    pub all: bool,
+    pub messages: Vec<String>,
+}
+
 /// keeping information about who created the contract
-pub const OWNER: Item<Addr> = Item::new("owner");
\ No newline at end of file
+pub const OWNER: Item<Addr> = Item::new("owner");
+/// an owner proposed by the current owner, waiting to accept the ownership
+/// the handover is done in two steps so the contract can't end up owned by a mistyped address
+/// the proposal is optionally limited by an expiry
+pub const PENDING_OWNER: Item<PendingOwner> = Item::new("pending_owner");
+
+#[cw_serde]
+pub struct PendingOwner {
+    pub owner: Addr,
+    pub expiry: Option<Expiration>,
+}
+
+/// roles granted by the owner, keyed by the role name and the holder address
+/// the owner implicitly holds every role, so only other addresses are stored here
+/// keeping the role name first allows listing all members of a role with a prefix
+pub const ROLES: Map<(&str, &Addr), ()> = Map::new("roles");
+
+/// totals donated by every single donor, accumulated per denom
+/// the coins which didn't meet the minimal donation are kept apart
+/// so the donor statement shows what counted and what didn't
+pub const DONORS: Map<&Addr, DonorTotals> = Map::new("donors");
+
+/// ranking of donors by their total qualifying donation of every denom
+/// keys are ordered ascending, so the amount is stored as a rank - inverted to make
+/// the top donor come first, with ties broken by the donor address
+/// updating a donor only touches their own entry, no matter how many donors there are
+pub const LEADERBOARD: Map<(&str, u128, &Addr), ()> = Map::new("leaderboard");
+
+pub fn leaderboard_rank(amount: Uint128) -> u128 {
+    u128::MAX - amount.u128()
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct DonorTotals {
+    pub qualifying: Vec<Coin>,
+    pub non_qualifying: Vec<Coin>,
+    // added by sponsors on top of the qualifying donations, never counted as donated by the donor
+    #[serde(default)]
+    pub matched: Vec<Coin>,
+}
+
+/// matching pools deposited by sponsors, one per sponsor
+/// qualifying donations of the pool denom are matched from them, in the order of sponsor addresses
+pub const MATCHING_POOLS: Map<&Addr, MatchingPool> = Map::new("matching_pools");
+
+#[cw_serde]
+pub struct MatchingPool {
+    // what is left of the deposit - it's held for the matching, so the owner can't withdraw it
+    pub remaining: Coin,
+    // matched amount for every donated token
+    pub ratio: Decimal,
+    // the most the pool matches in total, even if there are funds left
+    pub cap: Uint128,
+    pub matched: Uint128,
+    // matching stops at the deadline, then the sponsor can reclaim what is left
+    pub deadline: Timestamp,
+}
+
+impl MatchingPool {
+    // how much of the donated amount is matched right now
+    pub fn match_amount(&self, donated: Uint128, now: Timestamp) -> Uint128 {
+        if now >= self.deadline {
+            return Uint128::zero();
+        }
+
+        // a product too large to compute is way above what is left in the pool anyway
+        donated
+            .checked_mul_floor(self.ratio)
+            .unwrap_or(Uint128::MAX)
+            .min(self.remaining.amount)
+            .min(self.cap.saturating_sub(self.matched))
+    }
+}
+
+/// time-boxed fundraisers, keyed by a sequential campaign id
+/// only the latest campaign can be open, but older ones may still hold funds to claim or refund
+pub const CAMPAIGNS: Map<u64, Campaign> = Map::new("campaigns");
+/// id of the most recently opened campaign
+pub const LAST_CAMPAIGN_ID: Item<u64> = Item::new("last_campaign_id");
+/// funds escrowed for campaigns, keyed by the donor and the campaign id
+/// keeping the donor first allows to refund all donor's failed campaigns at once
+pub const CONTRIBUTIONS: Map<(&Addr, u64), Vec<Coin>> = Map::new("contributions");
+/// total of all funds held for campaigns, which can't be withdrawn
+pub const ESCROW: Item<Vec<Coin>> = Item::new("escrow");
+/// what the contributions added to the counters and the badge, so a refund can take it back
+pub const CONTRIBUTION_COUNTS: Map<(&Addr, u64), ContributionCount> =
+    Map::new("contribution_counts");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ContributionCount {
+    // the increment of the default counter, and of the named counters by their names
+    pub counter: Uint128,
+    pub named: Vec<(String, Uint128)>,
+    pub badge_units: Uint128,
+}
+
+#[cw_serde]
+pub struct Campaign {
+    pub goal: Vec<Coin>,
+    pub deadline: Timestamp,
+    pub raised: Vec<Coin>,
+    pub claimed: bool,
+}
+
+impl Campaign {
+    pub fn status(&self, now: Timestamp) -> CampaignStatus {
+        if now < self.deadline {
+            CampaignStatus::Open
+        } else if self.claimed {
+            CampaignStatus::Claimed
+        } else if self.goal_met() {
+            CampaignStatus::Succeeded
+        } else {
+            CampaignStatus::Failed
+        }
+    }
+
+    // every goal denom has to be raised in full
+    pub fn goal_met(&self) -> bool {
+        self.goal.iter().all(|goal| {
+            self.raised
+                .iter()
+                .any(|raised| raised.denom == goal.denom && raised.amount >= goal.amount)
+        })
+    }
+}
+
+/// memos posted with donations, keyed by a sequential id
+pub const MESSAGES: Map<u64, DonationMessage> = Map::new("messages");
+/// id of the most recently posted memo
+pub const LAST_MESSAGE_ID: Item<u64> = Item::new("last_message_id");
+
+#[cw_serde]
+pub struct DonationMessage {
+    pub sender: Addr,
+    pub memo: String,
+    pub time: Timestamp,
+}
+
+/// non-transferable badges minted to donors, keyed by the donor
+/// the donor address is also the badge token id
+pub const BADGES: Map<&Addr, Badge> = Map::new("badges");
+pub const BADGE_THRESHOLDS: Item<BadgeThresholds> = Item::new("badge_thresholds");
+
+#[cw_serde]
+pub struct Badge {
+    pub tier: BadgeTier,
+    pub donated_units: Uint128,
+}
+
+/// the layout of the counters before they were widened from u64 to Uint128
+/// the accessors use the same keys, so the stored values can be migrated in place
+pub mod legacy {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
+    use cw_storage_plus::{Item, Map};
+    use serde::{Deserialize, Serialize};
+
+    use crate::msg::{CounterChangeCause, CountingPolicy};
+
+    /// the single minimal donation the contract started with, before more denoms were accepted
+    pub const MINIMAL_DONATION: Item<Coin> = Item::new("minimal_donation");
+    pub const COUNTER: Item<Stored<u64, Uint128>> = Item::new("counter");
+    /// the snapshots of the counter, keyed by the height - the same as the SnapshotItem changelog
+    pub const COUNTER_CHANGELOG: Map<u64, Stored<ChangeSet<u64>, ChangeSet<Uint128>>> =
+        Map::new("counter__changelog");
+    pub const COUNTER_HISTORY: Map<u64, Stored<CounterChange, super::CounterChange>> =
+        Map::new("counter_history");
+    pub const COUNTERS: Map<&str, Stored<CounterState, super::CounterState>> = Map::new("counters");
+    /// where the unfinished migration stopped, absent once every map was walked through
+    pub const MIGRATION_CURSOR: Item<MigrationCursor> = Item::new("migration_cursor");
+
+    /// the map the migration is walking through, with the last key it rewrote
+    #[cw_serde]
+    pub enum MigrationCursor {
+        Counters { start_after: Option<String> },
+        History { start_after: Option<u64> },
+        Changelog { start_after: Option<u64> },
+    }
+
+    /// a value stored either in the old format or already in the wide one
+    /// the u64 is stored as a JSON number and the Uint128 as a string, so they never mix up
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(untagged)]
+    pub enum Stored<Old, Wide> {
+        Old(Old),
+        Wide(Wide),
+    }
+
+    /// the value before the change at the height, the way the SnapshotItem stores it
+    #[cw_serde]
+    pub struct ChangeSet<T> {
+        pub old: Option<T>,
+    }
+
+    #[cw_serde]
+    pub struct CounterChange {
+        pub value: u64,
+        pub height: u64,
+        pub time: Timestamp,
+        pub cause: CounterChangeCause,
+    }
+
+    #[cw_serde]
+    pub struct CounterState {
+        pub value: u64,
+        pub owner: Addr,
+        pub minimal_donations: Vec<Coin>,
+        pub counting_policy: CountingPolicy,
+    }
+}